@@ -0,0 +1,126 @@
+use crate::deco::{DecoAlgorithm, DecoError};
+use crate::environment::Environment;
+use crate::gas::Gas;
+use crate::segment::{Segment, SegmentType};
+use crate::surface_interval::SurfaceInterval;
+use crate::units::altitude::Altitude;
+use crate::units::depth::SURFACE;
+use crate::units::pressure::Pressure;
+use crate::units::rate::Rate;
+use time::Duration;
+
+/// Time spent at an altitude after a dive, such as driving over a mountain pass.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AltitudeLeg {
+    altitude: Altitude,
+    duration: Duration,
+}
+
+impl AltitudeLeg {
+    pub fn new(altitude: Altitude, duration: Duration) -> Self {
+        Self { altitude, duration }
+    }
+
+    pub fn altitude(&self) -> Altitude {
+        self.altitude
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// A point on the way to altitude where the ascent ceiling is above the ambient pressure.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AltitudeWarning {
+    leg: usize,
+    time: Duration,
+    ceiling: Pressure,
+    ambient: Pressure,
+}
+
+impl AltitudeWarning {
+    /// Index of the leg in which the ceiling was first exceeded.
+    pub fn leg(&self) -> usize {
+        self.leg
+    }
+
+    /// Time from the start of the leg at which the ceiling was first exceeded.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn ceiling(&self) -> Pressure {
+        self.ceiling
+    }
+
+    /// Ambient pressure at the altitude of the leg.
+    pub fn ambient(&self) -> Pressure {
+        self.ambient
+    }
+}
+
+/// Off-gas a diver on air at the altitude of `environment` before diving, starting from their
+/// current tissue loadings (usually saturated at the altitude they live at).
+pub fn acclimatise<T: DecoAlgorithm>(
+    deco: T,
+    duration: Duration,
+    environment: Environment,
+) -> Result<T, DecoError> {
+    SurfaceInterval::air(duration).apply(deco, environment)
+}
+
+/// Model travelling to altitude after a dive, breathing air at each leg in turn.
+/// Returns the model at the end of the journey, with a warning for each leg where the ascent
+/// ceiling rises above the ambient pressure.
+/// # Arguments
+/// * `deco` - Decompression model at the end of the dive, with the diver at the surface
+/// * `legs` - Altitudes reached during the journey, in order
+/// * `environment` - Environment of the dive site
+pub fn ascend_to_altitude<T: DecoAlgorithm>(
+    mut deco: T,
+    legs: &[AltitudeLeg],
+    environment: Environment,
+) -> Result<(T, Vec<AltitudeWarning>), DecoError> {
+    let air = Gas::new(21, 0, 79).unwrap();
+    let mut warnings = Vec::new();
+
+    for (index, leg) in legs.iter().enumerate() {
+        let leg_environment = environment.at_altitude(leg.altitude);
+        let ambient = leg_environment.surface_pressure();
+        let mut time = Duration::zero();
+        let mut warned = false;
+
+        // Check the ceiling on arrival and after every minute at this altitude
+        loop {
+            let ceiling = deco.ceiling();
+            if !warned && ceiling > ambient {
+                warnings.push(AltitudeWarning {
+                    leg: index,
+                    time,
+                    ceiling,
+                    ambient,
+                });
+                warned = true;
+            }
+
+            if time >= leg.duration {
+                break;
+            }
+            let step = Segment::new(
+                SegmentType::Bottom,
+                SURFACE,
+                SURFACE,
+                (leg.duration - time).min(Duration::minute()),
+                Rate::default(),
+                Rate::default(),
+            )?;
+            deco = deco.add_segment(&step, &air, leg_environment)?;
+            time += *step.time();
+        }
+    }
+
+    Ok((deco, warnings))
+}
@@ -0,0 +1,126 @@
+use crate::deco::zhl16::ZHL16;
+use crate::deco::DecoAlgorithm;
+use crate::dive_computer::{DiveComputer, DiveComputerError};
+use crate::gas::Gas;
+use crate::parameter::Parameters;
+use crate::tissue::Tissue;
+use crate::units::depth::Depth;
+use time::Duration;
+
+/// A sample from a recorded dive profile.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProfileSample {
+    time: Duration,
+    depth: Depth,
+    gas: Gas,
+}
+
+impl ProfileSample {
+    /// # Arguments
+    /// * `time` - Time of the sample, from the start of the dive
+    /// * `depth` - Depth of the diver at the time of the sample
+    /// * `gas` - Gas breathed from this sample until the next
+    pub fn new(time: Duration, depth: Depth, gas: Gas) -> Self {
+        Self { time, depth, gas }
+    }
+
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn depth(&self) -> Depth {
+        self.depth
+    }
+
+    pub fn gas(&self) -> Gas {
+        self.gas
+    }
+}
+
+/// State of the decompression model at a sample of a recorded dive.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnalysisPoint {
+    time: Duration,
+    depth: Depth,
+    tissue: Tissue,
+    leading_compartment: usize,
+    ceiling: Depth,
+    gf99: f64,
+    surf_gf: f64,
+}
+
+impl AnalysisPoint {
+    /// Replay a recorded profile through ZHL16, returning the state of the model at each sample.
+    /// The depth is assumed to change at a constant rate between samples.
+    /// # Arguments
+    /// * `deco` - Decompression model at the start of the dive
+    /// * `samples` - Samples of the profile, in order
+    /// * `parameters` - Dive parameters
+    /// # Errors
+    /// Returns an error if the samples are out of order.
+    pub fn replay(
+        deco: ZHL16,
+        samples: &[ProfileSample],
+        parameters: Parameters,
+    ) -> Result<Vec<Self>, DiveComputerError> {
+        let gas = match samples.first() {
+            Some(sample) => sample.gas,
+            None => return Ok(Vec::new()),
+        };
+        let mut computer = DiveComputer::new(deco, gas, parameters);
+
+        samples
+            .iter()
+            .map(|sample| {
+                computer.add_sample(sample.time, sample.depth)?;
+                if sample.gas != computer.gas() {
+                    computer.switch_gas(sample.time, sample.gas)?;
+                }
+
+                Ok(AnalysisPoint {
+                    time: sample.time,
+                    depth: sample.depth,
+                    tissue: computer.deco().tissue(),
+                    leading_compartment: computer.deco().leading_compartment(),
+                    ceiling: computer.ceiling(),
+                    gf99: computer.gf99(),
+                    surf_gf: computer.surf_gf(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn depth(&self) -> Depth {
+        self.depth
+    }
+
+    /// N2 and He pressures of each compartment.
+    pub fn tissue(&self) -> Tissue {
+        self.tissue
+    }
+
+    /// Index of the compartment controlling the ceiling.
+    pub fn leading_compartment(&self) -> usize {
+        self.leading_compartment
+    }
+
+    pub fn ceiling(&self) -> Depth {
+        self.ceiling
+    }
+
+    /// Largest supersaturation of any compartment at the sample depth (measured in %).
+    pub fn gf99(&self) -> f64 {
+        self.gf99
+    }
+
+    /// Largest supersaturation of any compartment if the diver surfaced (measured in %).
+    pub fn surf_gf(&self) -> f64 {
+        self.surf_gf
+    }
+}
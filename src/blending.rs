@@ -0,0 +1,203 @@
+use crate::gas::{Gas, GasModel};
+use crate::units::pressure::Pressure;
+use thiserror::Error;
+
+/// Tolerance used when solving for the fill (measured in litres of free gas per litre of water volume).
+const BLENDING_TOLERANCE: f64 = 1e-6;
+
+#[derive(Copy, Clone, Debug, Error, Eq, PartialEq, Hash)]
+pub enum BlendingError {
+    #[error("target pressure must be greater than zero")]
+    InvalidPressure,
+    #[error("top-up gas must contain nitrogen")]
+    InvalidTopUp,
+    #[error("target mix cannot be made with the given top-up gas")]
+    Unreachable,
+}
+
+/// A step in a partial pressure fill. Each pressure is the gauge reading to stop at.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FillStep {
+    /// Bleed the cylinder down to the given pressure.
+    BleedDown(Pressure),
+    /// Add helium up to the given pressure.
+    Helium(Pressure),
+    /// Add oxygen up to the given pressure.
+    Oxygen(Pressure),
+    /// Top up with a gas (usually air) to the given pressure.
+    TopUp(Gas, Pressure),
+}
+
+/// Calculate the partial pressure fill sequence to blend a target mix.
+/// Helium is added first, then oxygen, then the top-up gas.
+/// If the target cannot be reached from the current contents, the cylinder is bled down
+/// as little as possible first.
+/// # Arguments
+/// * `start_pressure` - Pressure of the gas already in the cylinder
+/// * `start_gas` - Gas already in the cylinder
+/// * `target_pressure` - Pressure of the finished fill
+/// * `target_gas` - Gas to blend
+/// * `top_up` - Gas used to top up the cylinder
+/// * `gas_model` - Whether to correct for real gas behaviour at high pressure
+pub fn fill_sequence(
+    start_pressure: Pressure,
+    start_gas: Gas,
+    target_pressure: Pressure,
+    target_gas: Gas,
+    top_up: Gas,
+    gas_model: GasModel,
+) -> Result<Vec<FillStep>, BlendingError> {
+    if target_pressure.0 <= 0.0 {
+        return Err(BlendingError::InvalidPressure);
+    }
+    if top_up.n2() == 0 {
+        return Err(BlendingError::InvalidTopUp);
+    }
+
+    // Work with free gas volumes per litre of water volume, which add linearly for real gases.
+    let target = gas_model.volume(&target_gas, 1.0, target_pressure);
+    let start = gas_model.volume(&start_gas, 1.0, Pressure(start_pressure.0.max(0.0)));
+    let (target_o2, target_he, target_n2) = (
+        target * target_gas.fr_o2(),
+        target * target_gas.fr_he(),
+        target * target_gas.fr_n2(),
+    );
+
+    // Each addition is linear in the volume left after bleeding (x), given as (a, b) for a + bx.
+    // Nitrogen can only come from the top-up gas.
+    let top_up_volume = (
+        target_n2 / top_up.fr_n2(),
+        -start_gas.fr_n2() / top_up.fr_n2(),
+    );
+    let helium = (
+        target_he - top_up_volume.0 * top_up.fr_he(),
+        -start_gas.fr_he() - top_up_volume.1 * top_up.fr_he(),
+    );
+    let oxygen = (
+        target_o2 - top_up_volume.0 * top_up.fr_o2(),
+        -start_gas.fr_o2() - top_up_volume.1 * top_up.fr_o2(),
+    );
+
+    // Find the range of volumes left in the cylinder for which every addition is positive.
+    let (mut low, mut high) = (0.0_f64, start);
+    for (a, b) in [top_up_volume, helium, oxygen].iter() {
+        if b.abs() < BLENDING_TOLERANCE {
+            if *a < -BLENDING_TOLERANCE {
+                return Err(BlendingError::Unreachable);
+            }
+        } else if *b > 0.0 {
+            low = low.max(-a / b);
+        } else {
+            high = high.min(a / -b);
+        }
+    }
+    if low > high + BLENDING_TOLERANCE {
+        return Err(BlendingError::Unreachable);
+    }
+
+    // Bleed as little as possible
+    let remaining = high.max(low);
+    let mut steps = Vec::new();
+    if remaining < start - BLENDING_TOLERANCE {
+        steps.push(FillStep::BleedDown(
+            gas_model.pressure(&start_gas, 1.0, remaining),
+        ));
+    }
+
+    let mut volumes = [
+        remaining * start_gas.fr_o2(),
+        remaining * start_gas.fr_he(),
+        remaining * start_gas.fr_n2(),
+    ];
+    let evaluate = |(a, b): (f64, f64)| (a + b * remaining).max(0.0);
+
+    let helium = evaluate(helium);
+    if helium > BLENDING_TOLERANCE {
+        volumes[1] += helium;
+        steps.push(FillStep::Helium(gas_model.mix_pressure(volumes, 1.0)));
+    }
+
+    let oxygen = evaluate(oxygen);
+    if oxygen > BLENDING_TOLERANCE {
+        volumes[0] += oxygen;
+        steps.push(FillStep::Oxygen(gas_model.mix_pressure(volumes, 1.0)));
+    }
+
+    let top_up_volume = evaluate(top_up_volume);
+    if top_up_volume > BLENDING_TOLERANCE {
+        volumes[0] += top_up_volume * top_up.fr_o2();
+        volumes[1] += top_up_volume * top_up.fr_he();
+        volumes[2] += top_up_volume * top_up.fr_n2();
+        steps.push(FillStep::TopUp(
+            top_up,
+            gas_model.mix_pressure(volumes, 1.0),
+        ));
+    }
+
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn air() -> Gas {
+        Gas::new(21, 0, 79).unwrap()
+    }
+
+    fn assert_pressure(actual: Pressure, expected: f64) {
+        assert!(
+            (actual.0 - expected).abs() < 0.01,
+            "{:?} {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn trimix_from_empty_topped_with_air() {
+        let trimix = Gas::new(21, 35, 44).unwrap();
+        let steps = fill_sequence(
+            Pressure(0.0),
+            air(),
+            Pressure(200.0),
+            trimix,
+            air(),
+            GasModel::Ideal,
+        )
+        .unwrap();
+
+        // 70 bar of helium, then 88 bar of nitrogen from 111.39 bar of air, which brings
+        // 23.39 bar of the 42 bar of oxygen needed
+        assert_eq!(steps.len(), 3);
+        match steps[..] {
+            [FillStep::Helium(helium), FillStep::Oxygen(oxygen), FillStep::TopUp(top_up, total)] => {
+                assert_pressure(helium, 70.0);
+                assert_pressure(oxygen, 70.0 + 42.0 - 88.0 / 0.79 * 0.21);
+                assert_eq!(top_up, air());
+                assert_pressure(total, 200.0);
+            }
+            _ => panic!("unexpected fill sequence {:?}", steps),
+        }
+    }
+
+    #[test]
+    fn real_gas_fill_reaches_target_pressure() {
+        let trimix = Gas::new(21, 35, 44).unwrap();
+        let steps = fill_sequence(
+            Pressure(0.0),
+            air(),
+            Pressure(200.0),
+            trimix,
+            air(),
+            GasModel::Real,
+        )
+        .unwrap();
+
+        match steps.last() {
+            Some(FillStep::TopUp(_, total)) => assert_pressure(*total, 200.0),
+            _ => panic!("unexpected fill sequence {:?}", steps),
+        }
+    }
+}
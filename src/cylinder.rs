@@ -0,0 +1,288 @@
+use crate::gas::{Gas, GasModel};
+use crate::units::consumption::GasConsumption;
+use crate::units::pressure::Pressure;
+use thiserror::Error;
+
+#[derive(Copy, Clone, Debug, Error, Eq, PartialEq, Hash)]
+pub enum CylinderError {
+    #[error("water volume must be greater than zero")]
+    InvalidVolume,
+    #[error("start pressure exceeds working pressure")]
+    Overfilled,
+    #[error("reserve pressure exceeds start pressure")]
+    InvalidReserve,
+}
+
+/// A cylinder carried by the diver.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cylinder {
+    water_volume: f64,
+    working_pressure: Pressure,
+    start_pressure: Pressure,
+    reserve_pressure: Pressure,
+    gas: Gas,
+}
+
+impl Cylinder {
+    /// Create a new cylinder.
+    /// # Arguments
+    /// * `water_volume` - Internal volume of the cylinder (measured in litres)
+    /// * `working_pressure` - Rated working pressure
+    /// * `start_pressure` - Pressure the cylinder is filled to at the start of the dive
+    /// * `reserve_pressure` - Pressure which should remain in the cylinder at the end of the dive
+    /// * `gas` - Gas held in the cylinder
+    pub fn new(
+        water_volume: f64,
+        working_pressure: Pressure,
+        start_pressure: Pressure,
+        reserve_pressure: Pressure,
+        gas: Gas,
+    ) -> Result<Self, CylinderError> {
+        if water_volume <= 0.0 {
+            return Err(CylinderError::InvalidVolume);
+        }
+        if start_pressure > working_pressure {
+            return Err(CylinderError::Overfilled);
+        }
+        if reserve_pressure > start_pressure {
+            return Err(CylinderError::InvalidReserve);
+        }
+
+        Ok(Self {
+            water_volume,
+            working_pressure,
+            start_pressure,
+            reserve_pressure,
+            gas,
+        })
+    }
+
+    pub fn water_volume(&self) -> f64 {
+        self.water_volume
+    }
+
+    pub fn working_pressure(&self) -> Pressure {
+        self.working_pressure
+    }
+
+    pub fn start_pressure(&self) -> Pressure {
+        self.start_pressure
+    }
+
+    pub fn reserve_pressure(&self) -> Pressure {
+        self.reserve_pressure
+    }
+
+    pub fn gas(&self) -> Gas {
+        self.gas
+    }
+
+    /// Volume of gas in the cylinder at a given pressure, at surface pressure, rounded to the
+    /// nearest litre.
+    pub fn volume_at(&self, pressure: Pressure, gas_model: GasModel) -> GasConsumption {
+        let volume = gas_model.volume(&self.gas, self.water_volume, pressure);
+        GasConsumption(volume.round().max(0.0) as u32)
+    }
+
+    /// Pressure left in the cylinder after a volume of gas has been used.
+    pub fn pressure_after(&self, used: GasConsumption, gas_model: GasModel) -> Pressure {
+        let remaining = (self.start_volume(gas_model).0 as f64 - used.0 as f64).max(0.0);
+        gas_model.pressure(&self.gas, self.water_volume, remaining)
+    }
+
+    /// Volume of gas available at the start of the dive.
+    pub fn start_volume(&self, gas_model: GasModel) -> GasConsumption {
+        self.volume_at(self.start_pressure, gas_model)
+    }
+}
+
+/// Gas drawn from a cylinder over a plan.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CylinderUsage {
+    cylinder: Cylinder,
+    gas_used: GasConsumption,
+    empty_at: Option<usize>,
+    gas_model: GasModel,
+}
+
+impl CylinderUsage {
+    pub fn cylinder(&self) -> Cylinder {
+        self.cylinder
+    }
+
+    pub fn gas_used(&self) -> GasConsumption {
+        self.gas_used
+    }
+
+    pub fn end_pressure(&self) -> Pressure {
+        self.cylinder.pressure_after(self.gas_used, self.gas_model)
+    }
+
+    /// Index of the plan segment during which the cylinder ran dry, if it did.
+    pub fn empty_at(&self) -> Option<usize> {
+        self.empty_at
+    }
+
+    /// Whether the cylinder was breathed below its reserve pressure.
+    pub fn below_reserve(&self) -> bool {
+        self.end_pressure() < self.cylinder.reserve_pressure
+    }
+
+    /// Draw the gas used by each segment, given as (segment index, gas, volume), from the
+    /// cylinders holding that gas. A segment may draw on several gases.
+    /// Cylinders holding the same gas are drained one after another, in the order given.
+    /// Demand which the cylinders cannot supply, either because no cylinder holds the gas or
+    /// because they are all empty, is returned as a shortfall for the segment. No shortfalls are
+    /// returned if no cylinders are given.
+    pub fn drain(
+        cylinders: &[Cylinder],
+        segment_gas_used: &[(usize, Gas, GasConsumption)],
+        gas_model: GasModel,
+    ) -> (Vec<Self>, Vec<GasShortfall>) {
+        let mut usage = cylinders
+            .iter()
+            .map(|&cylinder| Self {
+                cylinder,
+                gas_used: GasConsumption::default(),
+                empty_at: None,
+                gas_model,
+            })
+            .collect::<Vec<_>>();
+        let mut shortfalls = Vec::new();
+
+        for &(index, gas, consumed) in segment_gas_used {
+            let mut remaining = consumed.0;
+            for cylinder in usage
+                .iter_mut()
+                .filter(|cylinder| cylinder.cylinder.gas == gas && cylinder.empty_at.is_none())
+            {
+                if remaining == 0 {
+                    break;
+                }
+
+                let available = cylinder
+                    .cylinder
+                    .start_volume(gas_model)
+                    .0
+                    .saturating_sub(cylinder.gas_used.0);
+                let drawn = remaining.min(available);
+                cylinder.gas_used += GasConsumption(drawn);
+                remaining -= drawn;
+
+                if drawn == available {
+                    cylinder.empty_at = Some(index);
+                }
+            }
+
+            if remaining > 0 && !cylinders.is_empty() {
+                shortfalls.push(GasShortfall {
+                    segment: index,
+                    gas,
+                    volume: GasConsumption(remaining),
+                });
+            }
+        }
+
+        (usage, shortfalls)
+    }
+}
+
+/// Gas needed by a segment which none of the cylinders could supply.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GasShortfall {
+    segment: usize,
+    gas: Gas,
+    volume: GasConsumption,
+}
+
+impl GasShortfall {
+    /// Index of the segment in [`crate::plan::plan_result::PlanResult::segments`].
+    pub fn segment(&self) -> usize {
+        self.segment
+    }
+
+    pub fn gas(&self) -> Gas {
+        self.gas
+    }
+
+    /// Volume of gas which could not be supplied.
+    pub fn volume(&self) -> GasConsumption {
+        self.volume
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn air() -> Gas {
+        Gas::new(21, 0, 79).unwrap()
+    }
+
+    fn cylinder(gas: Gas) -> Cylinder {
+        Cylinder::new(12.0, Pressure(232.0), Pressure(200.0), Pressure(50.0), gas).unwrap()
+    }
+
+    #[test]
+    fn volume_is_rounded_to_the_nearest_litre() {
+        let cylinder = Cylinder::new(
+            11.1,
+            Pressure(232.0),
+            Pressure(200.0),
+            Pressure(50.0),
+            air(),
+        )
+        .unwrap();
+
+        // 11.1 L at 200 bar holds 2219.99... L, which used to be truncated to 2219 L
+        assert_eq!(cylinder.start_volume(GasModel::Ideal), GasConsumption(2220));
+    }
+
+    #[test]
+    fn cylinders_with_the_same_gas_are_drained_in_order() {
+        let cylinders = [cylinder(air()), cylinder(air())];
+        let (usage, shortfalls) = CylinderUsage::drain(
+            &cylinders,
+            &[
+                (0, air(), GasConsumption(2000)),
+                (1, air(), GasConsumption(1000)),
+            ],
+            GasModel::Ideal,
+        );
+
+        assert!(shortfalls.is_empty());
+        assert_eq!(usage[0].gas_used(), GasConsumption(2400));
+        assert_eq!(usage[0].empty_at(), Some(1));
+        assert_eq!(usage[1].gas_used(), GasConsumption(600));
+        assert_eq!(usage[1].empty_at(), None);
+        assert_eq!(usage[1].end_pressure(), Pressure(150.0));
+    }
+
+    #[test]
+    fn demand_exceeding_supply_is_a_shortfall() {
+        let nitrox = Gas::new(50, 0, 50).unwrap();
+        let cylinders = [cylinder(air())];
+        let (usage, shortfalls) = CylinderUsage::drain(
+            &cylinders,
+            &[
+                (0, air(), GasConsumption(3000)),
+                (1, nitrox, GasConsumption(500)),
+            ],
+            GasModel::Ideal,
+        );
+
+        assert_eq!(usage[0].gas_used(), GasConsumption(2400));
+        assert_eq!(usage[0].empty_at(), Some(0));
+        assert!(usage[0].below_reserve());
+        assert_eq!(shortfalls.len(), 2);
+        assert_eq!(shortfalls[0].segment(), 0);
+        assert_eq!(shortfalls[0].gas(), air());
+        assert_eq!(shortfalls[0].volume(), GasConsumption(600));
+        assert_eq!(shortfalls[1].segment(), 1);
+        assert_eq!(shortfalls[1].gas(), nitrox);
+        assert_eq!(shortfalls[1].volume(), GasConsumption(500));
+    }
+}
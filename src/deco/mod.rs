@@ -1,24 +1,157 @@
 use crate::environment::Environment;
 use crate::gas::Gas;
-use crate::segment::Segment;
+use crate::segment::{DiveSegmentError, Segment};
 
 use crate::tissue::Tissue;
 use crate::units::depth::Depth;
+use crate::units::pressure::Pressure;
 use crate::units::rate::Rate;
+use thiserror::Error;
 
+pub mod vpm;
 pub mod zhl16;
 
 pub const TISSUE_COUNT: usize = 16;
 
+#[derive(Copy, Clone, Debug, Error, Eq, PartialEq, Hash)]
+pub enum DecoError {
+    #[error("no-deco segment applied to deco algorithm")]
+    NoDecoSegment,
+    #[error("ascent ceiling is above the surface but no NDL was found")]
+    NoNdl,
+    #[error("invalid segment: {0}")]
+    Segment(#[from] DiveSegmentError),
+}
+
+/// State of a single compartment of a decompression model.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompartmentReport {
+    p_n2: Pressure,
+    p_he: Pressure,
+    ceiling: Pressure,
+    m_value: Pressure,
+    supersaturation: f64,
+}
+
+impl CompartmentReport {
+    /// # Arguments
+    /// * `p_n2` - N2 pressure in the compartment
+    /// * `p_he` - He pressure in the compartment
+    /// * `ceiling` - Lowest ambient pressure tolerated by the compartment
+    /// * `m_value` - Largest inert gas pressure tolerated by the compartment at the current depth
+    /// * `supersaturation` - Supersaturation at the current depth, as a percentage of the
+    ///   M-value gradient
+    pub fn new(
+        p_n2: Pressure,
+        p_he: Pressure,
+        ceiling: Pressure,
+        m_value: Pressure,
+        supersaturation: f64,
+    ) -> Self {
+        Self {
+            p_n2,
+            p_he,
+            ceiling,
+            m_value,
+            supersaturation,
+        }
+    }
+
+    /// Build a report for every compartment of a model.
+    pub(crate) fn collect(
+        tissue: &Tissue,
+        ceilings: &[Pressure; TISSUE_COUNT],
+        m_values: &[Pressure; TISSUE_COUNT],
+        supersaturation: &[f64; TISSUE_COUNT],
+    ) -> [Self; TISSUE_COUNT] {
+        let mut reports = [Self::default(); TISSUE_COUNT];
+        for (i, report) in reports.iter_mut().enumerate() {
+            *report = Self::new(
+                tissue.p_n2()[i],
+                tissue.p_he()[i],
+                ceilings[i],
+                m_values[i],
+                supersaturation[i],
+            );
+        }
+        reports
+    }
+
+    pub fn p_n2(&self) -> Pressure {
+        self.p_n2
+    }
+
+    pub fn p_he(&self) -> Pressure {
+        self.p_he
+    }
+
+    pub fn ceiling(&self) -> Pressure {
+        self.ceiling
+    }
+
+    pub fn m_value(&self) -> Pressure {
+        self.m_value
+    }
+
+    /// Supersaturation at the current depth (measured in %). Negative if the compartment is
+    /// on-gassing.
+    pub fn supersaturation(&self) -> f64 {
+        self.supersaturation
+    }
+}
+
 pub trait DecoAlgorithm {
-    fn add_segment(self, segment: &Segment, gas: &Gas, environment: Environment) -> Self;
+    /// Add a segment to the model.
+    /// # Errors
+    /// Returns an error if the segment is a no-deco segment.
+    fn add_segment(
+        self,
+        segment: &Segment,
+        gas: &Gas,
+        environment: Environment,
+    ) -> Result<Self, DecoError>
+    where
+        Self: Sized;
+    /// Add a segment breathed from a closed circuit loop, with oxygen added to the diluent to
+    /// hold a ppO2 setpoint. The model is loaded with the exact inert fractions of the loop.
+    /// # Errors
+    /// Returns an error if the segment is a no-deco segment.
+    fn add_loop_segment(
+        self,
+        segment: &Segment,
+        diluent: &Gas,
+        setpoint: Pressure,
+        environment: Environment,
+    ) -> Result<Self, DecoError>
+    where
+        Self: Sized;
     fn get_stops(
         self,
         ascent_rate: Rate,
         descent_rate: Rate,
         gas: &Gas,
         environment: Environment,
-    ) -> Vec<Segment>;
+    ) -> Result<Vec<Segment>, DecoError>;
     fn tissue(&self) -> Tissue;
     fn model_depth(&self) -> Depth;
+    /// Current ascent ceiling, as the lowest ambient pressure the diver may ascend to.
+    fn ceiling(&self) -> Pressure;
+    /// Current ascent ceiling as a depth. The surface if no decompression is required.
+    fn ceiling_depth(&self, environment: Environment) -> Depth {
+        self.ceiling().equivalent_depth(environment)
+    }
+    /// Index of the compartment controlling the ascent ceiling.
+    fn leading_compartment(&self) -> usize;
+    /// State of every compartment at the current depth.
+    fn compartments(&self, environment: Environment) -> [CompartmentReport; TISSUE_COUNT];
+    /// Largest supersaturation of any compartment at the current depth,
+    /// as a percentage of its M-value gradient.
+    fn gf99(&self, environment: Environment) -> f64;
+    /// Largest supersaturation of any compartment if the diver surfaced immediately,
+    /// as a percentage of its M-value gradient.
+    fn surf_gf(&self, environment: Environment) -> f64;
+    /// Reset state that only applies to a single dive (such as the first deco depth),
+    /// keeping the tissue loadings.
+    fn reset_dive_state(self) -> Self;
 }
@@ -0,0 +1,87 @@
+use crate::deco::vpm::VPM;
+use crate::deco::TISSUE_COUNT;
+use crate::tissue::Tissue;
+use crate::units::depth::{Depth, SURFACE};
+use crate::units::pressure::Pressure;
+use time::Duration;
+
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VPMBuilder {
+    tissue: Tissue,
+    diver_depth: Depth,
+    first_deco_depth: Option<Depth>,
+    conservatism: u8,
+    deco_increment: Duration,
+}
+
+impl VPMBuilder {
+    /// Create a new builder with default values (diver on the surface, using conservatism 0).
+    pub fn new() -> Self {
+        Self {
+            tissue: Tissue::default(),
+            diver_depth: SURFACE,
+            first_deco_depth: None,
+            conservatism: 0,
+            deco_increment: Duration::minute(),
+        }
+    }
+
+    /// Set the tissue compartments to use.
+    pub fn tissue(&mut self, tissue: Tissue) -> &mut Self {
+        self.tissue = tissue;
+        self
+    }
+
+    /// Set the current depth of the diver.
+    pub fn current_depth(&mut self, depth: Depth) -> &mut Self {
+        self.diver_depth = depth;
+        self
+    }
+
+    /// Set the first deco depth of the diver.
+    pub fn first_deco_depth(&mut self, depth: Depth) -> &mut Self {
+        self.first_deco_depth = if depth == SURFACE { None } else { Some(depth) };
+
+        self
+    }
+
+    /// Set the conservatism level to use (0 to 5). Higher levels are clamped to 5.
+    pub fn conservatism(&mut self, level: u8) -> &mut Self {
+        self.conservatism = level.min(5);
+        self
+    }
+
+    pub fn deco_stop_increment(&mut self, increment: Duration) -> &mut Self {
+        self.deco_increment = increment;
+        self
+    }
+
+    /// Finish the builder and create a VPM struct.
+    pub fn finish(&mut self) -> VPM {
+        let (radius_n2, radius_he) = VPM::base_critical_radii(self.conservatism);
+        VPM {
+            tissue: self.tissue,
+            diver_depth: self.diver_depth,
+            first_deco_depth: self.first_deco_depth,
+            conservatism: self.conservatism,
+            max_crushing_pressure_n2: [0.0; TISSUE_COUNT],
+            max_crushing_pressure_he: [0.0; TISSUE_COUNT],
+            onset_tension: [0.0; TISSUE_COUNT],
+            max_ambient_pressure: Pressure::default(),
+            max_actual_gradient: [0.0; TISSUE_COUNT],
+            dive_end_radius_n2: [radius_n2; TISSUE_COUNT],
+            dive_end_radius_he: [radius_he; TISSUE_COUNT],
+            surface_time: Duration::zero(),
+            dive_started: self.diver_depth > SURFACE,
+            run_time: Duration::zero(),
+            deco_zone_start: None,
+            deco_increment: self.deco_increment,
+        }
+    }
+}
+
+impl Default for VPMBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -3,7 +3,7 @@ use crate::deco::zhl16::tissue_constants::TissueConstants;
 use crate::deco::zhl16::variant::Variant;
 use crate::deco::zhl16::ZHL16;
 use crate::tissue::Tissue;
-use crate::units::depth::Depth;
+use crate::units::depth::{Depth, SURFACE};
 use time::Duration;
 
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
@@ -21,7 +21,7 @@ impl ZHL16Builder {
     pub fn new() -> Self {
         Self {
             tissue: Tissue::default(),
-            diver_depth: Depth(0),
+            diver_depth: SURFACE,
             first_deco_depth: None,
             gradient_factor: GradientFactor::default(),
             constants: TissueConstants::new_by_variant(Variant::B),
@@ -43,7 +43,7 @@ impl ZHL16Builder {
 
     /// Set the first deco depth of the diver.
     pub fn first_deco_depth(&mut self, depth: Depth) -> &mut Self {
-        self.first_deco_depth = if depth == Depth(0) { None } else { Some(depth) };
+        self.first_deco_depth = if depth == SURFACE { None } else { Some(depth) };
 
         self
     }
@@ -1,16 +1,16 @@
 use crate::deco::zhl16::gradient_factor::GradientFactor;
 use crate::deco::zhl16::tissue_constants::TissueConstants;
-use crate::deco::{DecoAlgorithm, TISSUE_COUNT};
+use crate::deco::{CompartmentReport, DecoAlgorithm, DecoError, TISSUE_COUNT};
 use crate::environment::Environment;
 use crate::gas::Gas;
 use crate::segment::{Segment, SegmentType};
 use crate::tissue::Tissue;
-use crate::units::depth::Depth;
+use crate::units::depth::{Depth, SURFACE};
 use crate::units::pressure::Pressure;
 use crate::units::rate::Rate;
 use crate::util::time_taken;
 use itertools::izip;
-use std::f64::consts::{E, LN_2};
+use std::cmp::Ordering;
 use time::Duration;
 
 pub mod builder;
@@ -37,39 +37,13 @@ pub struct ZHL16 {
 
 impl ZHL16 {
     fn add_flat_segment_inner(&mut self, segment: &Segment, gas: &Gas, environment: Environment) {
-        for (pressure, half_life) in self
-            .tissue
-            .p_n2_mut()
-            .iter_mut()
-            .zip(self.tissue_constants.n2_hl().iter())
-        {
-            let pi = segment.end_depth().compensated_pressure(environment) * Pressure(gas.fr_n2());
-            *pressure = ZHL16::flat_loading(
-                *pressure,
-                pi,
-                segment.time().whole_seconds() as f64 / 60.0,
-                *half_life,
-            );
-        }
-
-        for (pressure, half_life) in self
-            .tissue
-            .p_he_mut()
-            .iter_mut()
-            .zip(self.tissue_constants.he_hl().iter())
-        {
-            let pi = segment.end_depth().compensated_pressure(environment) * Pressure(gas.fr_he());
-            *pressure = ZHL16::flat_loading(
-                *pressure,
-                pi,
-                segment.time().whole_seconds() as f64 / 60.0,
-                *half_life,
-            );
-        }
-    }
-
-    fn flat_loading(po: Pressure, pi: Pressure, time: f64, half_life: f64) -> Pressure {
-        po + (pi - po) * Pressure(1.0 - (2.0_f64.powf(-1.0 * time / half_life)))
+        self.tissue.add_flat_segment(
+            segment,
+            gas,
+            environment,
+            &self.tissue_constants.n2_hl(),
+            &self.tissue_constants.he_hl(),
+        );
     }
 
     fn add_depth_change_segment_inner(
@@ -78,60 +52,16 @@ impl ZHL16 {
         gas: &Gas,
         environment: Environment,
     ) {
-        let rate = if segment.start_depth() < segment.end_depth() {
-            segment.descent_rate()
-        } else {
-            segment.ascent_rate()
-        };
-
-        let time_fr = segment.time().whole_seconds() as f64 / 60.0;
-
-        // Nitrogen
-        for (pressure, half_life) in self
-            .tissue
-            .p_n2_mut()
-            .iter_mut()
-            .zip(self.tissue_constants.n2_hl().iter())
-        {
-            let pio =
-                segment.start_depth().compensated_pressure(environment) * Pressure(gas.fr_n2());
-            let r = (rate.0 as f64 / 10.0) * gas.fr_n2();
-            let k = LN_2 / half_life;
-
-            *pressure = ZHL16::depth_change_loading(time_fr, *pressure, pio, r, k);
-        }
-
-        // Helium
-        for (pressure, half_life) in self
-            .tissue
-            .p_he_mut()
-            .iter_mut()
-            .zip(self.tissue_constants.he_hl().iter())
-        {
-            let pio =
-                segment.start_depth().compensated_pressure(environment) * Pressure(gas.fr_he());
-            let r = (rate.0 as f64 / 10.0) * gas.fr_he();
-            let k = LN_2 / half_life;
-
-            *pressure = ZHL16::depth_change_loading(time_fr, *pressure, pio, r, k);
-        }
-
+        self.tissue.add_depth_change_segment(
+            segment,
+            gas,
+            environment,
+            &self.tissue_constants.n2_hl(),
+            &self.tissue_constants.he_hl(),
+        );
         self.diver_depth = segment.end_depth();
     }
 
-    fn depth_change_loading(
-        time: f64,
-        initial: Pressure,
-        initial_ambient: Pressure,
-        r: f64,
-        k: f64,
-    ) -> Pressure {
-        Pressure(
-            initial_ambient.0 + r * (time - (1.0 / k))
-                - ((initial_ambient.0 - initial.0 - (r / k)) * E.powf(-1.0 * k * time)),
-        )
-    }
-
     fn update_first_deco_depth(&mut self, depth: Depth) {
         self.first_deco_depth = self.first_deco_depth.or(Some(depth));
     }
@@ -139,11 +69,11 @@ impl ZHL16 {
     fn fr_gf_at_depth(&self, depth: Depth) -> f64 {
         self.first_deco_depth
             .map(|deco_depth| {
-                if deco_depth > Depth(0) {
+                if deco_depth > SURFACE {
                     // Only calculate if below surface
                     self.gf.fr_high()
-                        + ((self.gf.fr_high() - self.gf.fr_low()) / (0.0 - deco_depth.0 as f64))
-                            * (depth.0 as f64)
+                        + ((self.gf.fr_high() - self.gf.fr_low()) / (0.0 - deco_depth.metres()))
+                            * depth.metres()
                 } else {
                     // By definition GFH is on the surface
                     self.gf.fr_high()
@@ -153,7 +83,7 @@ impl ZHL16 {
             .unwrap_or_else(|| self.gf.fr_high())
     }
 
-    fn tissue_ab_value(n2_ab: f64, he_ab: f64, p_n2: Pressure, p_he: Pressure) -> f64 {
+    pub(crate) fn tissue_ab_value(n2_ab: f64, he_ab: f64, p_n2: Pressure, p_he: Pressure) -> f64 {
         (n2_ab * p_n2.0 + he_ab * p_he.0) / (p_n2 + p_he).0
     }
 
@@ -161,7 +91,7 @@ impl ZHL16 {
         Pressure(((p_n2 + p_he).0 - (a * gf)) / (gf / b + 1.0 - gf))
     }
 
-    fn ascent_ceiling(&self, fr_gf_override: Option<f64>) -> Pressure {
+    fn compartment_ceilings(&self, fr_gf_override: Option<f64>) -> [Pressure; TISSUE_COUNT] {
         let mut ceilings: [Pressure; TISSUE_COUNT] = [Pressure::default(); TISSUE_COUNT];
         let gf = fr_gf_override.unwrap_or_else(|| {
             self.first_deco_depth
@@ -183,7 +113,35 @@ impl ZHL16 {
             *ceil = ZHL16::tissue_ceiling(gf, *p_n2, *p_he, a, b);
         }
 
-        Pressure(ceilings.iter().fold(f64::NAN, |prev, &cur| prev.max(cur.0)))
+        ceilings
+    }
+
+    fn ascent_ceiling(&self, fr_gf_override: Option<f64>) -> Pressure {
+        Pressure(
+            self.compartment_ceilings(fr_gf_override)
+                .iter()
+                .fold(f64::NAN, |prev, &cur| prev.max(cur.0)),
+        )
+    }
+
+    /// Ascent ceiling using the high gradient factor, which applies at the surface.
+    pub(crate) fn surface_ceiling(&self) -> Pressure {
+        self.ascent_ceiling(Some(self.gf.fr_high()))
+    }
+
+    /// Supersaturation of each compartment at the current depth, as a percentage of its M-value
+    /// gradient. Negative values are compartments which are on-gassing.
+    /// GF99 ([`DecoAlgorithm::gf99`]) is the largest of these.
+    pub fn supersaturation(&self, environment: Environment) -> [f64; TISSUE_COUNT] {
+        self.tissue_constants
+            .supersaturation(&self.tissue, self.diver_depth.pressure(environment))
+    }
+
+    /// Supersaturation of each compartment if the diver surfaced immediately, as a percentage of its
+    /// M-value gradient. SurfGF ([`DecoAlgorithm::surf_gf`]) is the largest of these.
+    pub fn surface_supersaturation(&self, environment: Environment) -> [f64; TISSUE_COUNT] {
+        self.tissue_constants
+            .supersaturation(&self.tissue, environment.surface_pressure())
     }
 
     fn find_next_stop(
@@ -192,13 +150,13 @@ impl ZHL16 {
         descent_rate: Rate,
         gas: &Gas,
         environment: Environment,
-    ) -> (Option<Segment>, Segment) {
-        let stop_depth = Depth(
-            (3.0 * (self
+    ) -> Result<(Option<Segment>, Segment), DecoError> {
+        let stop_depth = Depth::from_metres(
+            3.0 * (self
                 .ascent_ceiling(None)
                 .precise_equivalent_depth(environment)
                 / 3.0)
-                .ceil()) as u32,
+                .ceil(),
         );
         let mut stop_time = self.deco_increment;
 
@@ -214,9 +172,8 @@ impl ZHL16 {
                     time_taken(ascent_rate, virtual_model.diver_depth, stop_depth),
                     ascent_rate,
                     descent_rate,
-                )
-                .unwrap();
-                virtual_model = virtual_model.add_segment(&segment, gas, environment);
+                )?;
+                virtual_model = virtual_model.add_segment(&segment, gas, environment)?;
                 asc_segment = Some(segment);
             }
 
@@ -228,24 +185,24 @@ impl ZHL16 {
                 stop_time,
                 ascent_rate,
                 descent_rate,
-            )
-            .unwrap();
+            )?;
 
-            virtual_model = virtual_model.add_segment(&deco_segment, gas, environment);
+            virtual_model = virtual_model.add_segment(&deco_segment, gas, environment)?;
             virtual_model.update_first_deco_depth(stop_depth);
 
             // Break if cleared to proceed to the next stop
             if virtual_model.ascent_ceiling(None)
-                < stop_depth.pressure(environment) - Depth(3).pressure(environment) + environment.altitude().atmospheric_pressure()
+                < stop_depth.pressure(environment) - Depth::from_metres(3.0).pressure(environment)
+                    + environment.surface_pressure()
             {
-                break (asc_segment, deco_segment);
+                break Ok((asc_segment, deco_segment));
             } else {
                 stop_time += self.deco_increment
             }
         }
     }
 
-    fn find_ndl(&self, gas: &Gas, environment: Environment) -> Option<Duration> {
+    fn find_ndl(&self, gas: &Gas, environment: Environment) -> Result<Option<Duration>, DecoError> {
         let mut ndl_duration = Duration::zero();
         loop {
             let mut virtual_deco = *self;
@@ -256,21 +213,21 @@ impl ZHL16 {
                 ndl_duration,
                 Rate::default(),
                 Rate::default(),
-            )
-            .unwrap();
+            )?;
 
-            virtual_deco = virtual_deco.add_segment(&segment, gas, environment);
-            if virtual_deco.ascent_ceiling(Some(self.gf.fr_high())) > environment.altitude().atmospheric_pressure() {
+            virtual_deco = virtual_deco.add_segment(&segment, gas, environment)?;
+            if virtual_deco.ascent_ceiling(Some(self.gf.fr_high())) > environment.surface_pressure()
+            {
                 if ndl_duration == Duration::zero() {
                     // No NDL
-                    break None;
+                    break Ok(None);
                 } else {
                     // Return actual value
-                    break Some(ndl_duration);
+                    break Ok(Some(ndl_duration));
                 }
             } else {
                 if ndl_duration > Duration::minutes(999) {
-                    break Some(Duration::minutes(999));
+                    break Ok(Some(Duration::minutes(999)));
                 }
                 ndl_duration += Duration::minute();
             }
@@ -279,9 +236,14 @@ impl ZHL16 {
 }
 
 impl DecoAlgorithm for ZHL16 {
-    fn add_segment(mut self, segment: &Segment, gas: &Gas, environment: Environment) -> Self {
+    fn add_segment(
+        mut self,
+        segment: &Segment,
+        gas: &Gas,
+        environment: Environment,
+    ) -> Result<Self, DecoError> {
         match segment.segment_type() {
-            SegmentType::NoDeco => panic!("no-deco segment applied to deco algorithm"),
+            SegmentType::NoDeco => return Err(DecoError::NoDecoSegment),
             SegmentType::DecoStop => {
                 self.add_flat_segment_inner(segment, gas, environment);
                 self.update_first_deco_depth(segment.start_depth());
@@ -295,7 +257,33 @@ impl DecoAlgorithm for ZHL16 {
         }
         self.diver_depth = segment.end_depth();
 
-        self
+        Ok(self)
+    }
+
+    fn add_loop_segment(
+        mut self,
+        segment: &Segment,
+        diluent: &Gas,
+        setpoint: Pressure,
+        environment: Environment,
+    ) -> Result<Self, DecoError> {
+        if segment.segment_type() == SegmentType::NoDeco {
+            return Err(DecoError::NoDecoSegment);
+        }
+        self.tissue.add_loop_segment(
+            segment,
+            diluent,
+            setpoint,
+            environment,
+            &self.tissue_constants.n2_hl(),
+            &self.tissue_constants.he_hl(),
+        );
+        if segment.segment_type() == SegmentType::DecoStop {
+            self.update_first_deco_depth(segment.start_depth());
+        }
+        self.diver_depth = segment.end_depth();
+
+        Ok(self)
     }
 
     fn get_stops(
@@ -304,42 +292,38 @@ impl DecoAlgorithm for ZHL16 {
         descent_rate: Rate,
         gas: &Gas,
         environment: Environment,
-    ) -> Vec<Segment> {
+    ) -> Result<Vec<Segment>, DecoError> {
         let mut stops: Vec<Segment> = vec![];
 
-        if self.ascent_ceiling(Some(self.gf.fr_high())) < environment.altitude().atmospheric_pressure() {
-            stops.push(
-                Segment::new(
-                    SegmentType::NoDeco,
-                    self.diver_depth,
-                    self.diver_depth,
-                    self.find_ndl(gas, environment)
-                        .expect("ascent ceiling < 1.0 but no ndl"),
-                    Rate::default(),
-                    Rate::default(),
-                )
-                .unwrap(),
-            );
-            return stops;
+        if self.ascent_ceiling(Some(self.gf.fr_high())) < environment.surface_pressure() {
+            stops.push(Segment::new(
+                SegmentType::NoDeco,
+                self.diver_depth,
+                self.diver_depth,
+                self.find_ndl(gas, environment)?.ok_or(DecoError::NoNdl)?,
+                Rate::default(),
+                Rate::default(),
+            )?);
+            return Ok(stops);
         }
 
         loop {
             let (asc_to_stop, stop) =
-                self.find_next_stop(ascent_rate, descent_rate, gas, environment);
+                self.find_next_stop(ascent_rate, descent_rate, gas, environment)?;
 
             self.update_first_deco_depth(stop.end_depth());
 
             // Make sure no AscDesc is made if same depth, but deco necessary
             if let Some(asc_to_stop) = asc_to_stop {
-                self = self.add_segment(&asc_to_stop, gas, environment);
+                self = self.add_segment(&asc_to_stop, gas, environment)?;
                 stops.push(asc_to_stop);
             }
 
-            self = self.add_segment(&stop, gas, environment);
+            self = self.add_segment(&stop, gas, environment)?;
             stops.push(stop);
 
-            if self.ascent_ceiling(None) < environment.altitude().atmospheric_pressure() {
-                break stops;
+            if self.ascent_ceiling(None) < environment.surface_pressure() {
+                break Ok(stops);
             }
         }
     }
@@ -351,4 +335,48 @@ impl DecoAlgorithm for ZHL16 {
     fn model_depth(&self) -> Depth {
         self.diver_depth
     }
+
+    fn ceiling(&self) -> Pressure {
+        if self.diver_depth == SURFACE {
+            // By definition GFH is on the surface, whether or not decompression has started
+            self.surface_ceiling()
+        } else {
+            self.ascent_ceiling(None)
+        }
+    }
+
+    fn leading_compartment(&self) -> usize {
+        self.compartment_ceilings(None)
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+            .map(|(compartment, _)| compartment)
+            .unwrap_or_default()
+    }
+
+    fn compartments(&self, environment: Environment) -> [CompartmentReport; TISSUE_COUNT] {
+        CompartmentReport::collect(
+            &self.tissue,
+            &self.compartment_ceilings(None),
+            &self
+                .tissue_constants
+                .m_values(&self.tissue, self.diver_depth.pressure(environment)),
+            &self.supersaturation(environment),
+        )
+    }
+
+    fn gf99(&self, environment: Environment) -> f64 {
+        self.tissue_constants
+            .leading_supersaturation(&self.tissue, self.diver_depth.pressure(environment))
+    }
+
+    fn surf_gf(&self, environment: Environment) -> f64 {
+        self.tissue_constants
+            .leading_supersaturation(&self.tissue, environment.surface_pressure())
+    }
+
+    fn reset_dive_state(mut self) -> Self {
+        self.first_deco_depth = None;
+        self
+    }
 }
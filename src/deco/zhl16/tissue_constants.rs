@@ -1,5 +1,8 @@
 use crate::deco::zhl16::variant::Variant;
+use crate::deco::zhl16::ZHL16;
 use crate::deco::TISSUE_COUNT;
+use crate::tissue::Tissue;
+use crate::units::pressure::Pressure;
 
 /// N2 half-lives for the ZHL-16B deco algorithm.
 pub const ZHL16B_N2_HALFLIFE: [f64; 16] = [
@@ -127,6 +130,46 @@ impl TissueConstants {
         self.he_hl
     }
 
+    /// Supersaturation of each compartment at an ambient pressure, as a percentage of the
+    /// M-value gradient (0% when saturated at ambient pressure, 100% at the M-value).
+    pub(crate) fn supersaturation(
+        &self,
+        tissue: &Tissue,
+        ambient: Pressure,
+    ) -> [f64; TISSUE_COUNT] {
+        let m_values = self.m_values(tissue, ambient);
+        let mut supersaturation = [0.0; TISSUE_COUNT];
+
+        for (i, gf) in supersaturation.iter_mut().enumerate() {
+            let loading = tissue.p_n2()[i] + tissue.p_he()[i];
+            *gf = (loading - ambient).0 / (m_values[i] - ambient).0 * 100.0;
+        }
+
+        supersaturation
+    }
+
+    /// Largest inert gas pressure tolerated by each compartment at an ambient pressure.
+    pub(crate) fn m_values(&self, tissue: &Tissue, ambient: Pressure) -> [Pressure; TISSUE_COUNT] {
+        let mut m_values = [Pressure::default(); TISSUE_COUNT];
+
+        for (i, m_value) in m_values.iter_mut().enumerate() {
+            let (p_n2, p_he) = (tissue.p_n2()[i], tissue.p_he()[i]);
+            let a = ZHL16::tissue_ab_value(self.n2_a[i], self.he_a[i], p_n2, p_he);
+            let b = ZHL16::tissue_ab_value(self.n2_b[i], self.he_b[i], p_n2, p_he);
+            *m_value = Pressure(ambient.0 / b + a);
+        }
+
+        m_values
+    }
+
+    /// Largest supersaturation of any compartment at an ambient pressure, or zero if every
+    /// compartment is on-gassing.
+    pub(crate) fn leading_supersaturation(&self, tissue: &Tissue, ambient: Pressure) -> f64 {
+        self.supersaturation(tissue, ambient)
+            .iter()
+            .fold(0.0, |max, &gf| max.max(gf))
+    }
+
     pub fn new_by_variant(variant: Variant) -> Self {
         match variant {
             Variant::B => Self::new(
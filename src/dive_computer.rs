@@ -0,0 +1,316 @@
+use crate::deco::{DecoAlgorithm, DecoError};
+use crate::gas::Gas;
+use crate::parameter::Parameters;
+use crate::segment::{DiveSegmentError, Segment, SegmentType};
+use crate::units::depth::{Depth, SURFACE};
+use crate::units::rate::Rate;
+use crate::util::time_taken;
+use thiserror::Error;
+use time::Duration;
+
+#[derive(Copy, Clone, Debug, Error, Eq, PartialEq, Hash)]
+pub enum DiveComputerError {
+    #[error("sample is earlier than the previous sample")]
+    OutOfOrder,
+    #[error("invalid segment: {0}")]
+    Segment(#[from] DiveSegmentError),
+    #[error("decompression model error: {0}")]
+    Deco(#[from] DecoError),
+}
+
+/// A real-time decompression engine fed by depth samples, as used by a dive computer.
+/// The decompression model is advanced between each pair of samples, assuming a constant rate
+/// of depth change between them.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiveComputer<T>
+where
+    T: DecoAlgorithm + Clone,
+{
+    deco: T,
+    gas: Gas,
+    time: Duration,
+    parameters: Parameters,
+}
+
+impl<T> DiveComputer<T>
+where
+    T: DecoAlgorithm + Clone,
+{
+    /// Create a new dive computer. The diver starts at the depth of the model.
+    /// # Arguments
+    /// * `deco` - Decompression algorithm to use
+    /// * `gas` - Gas breathed at the start of the dive
+    /// * `parameters` - Dive parameters. Ascent and descent rates are used to calculate stops.
+    pub fn new(deco: T, gas: Gas, parameters: Parameters) -> Self {
+        Self {
+            deco,
+            gas,
+            time: Duration::zero(),
+            parameters,
+        }
+    }
+
+    /// Add a depth sample taken at `time` (from the start of the dive).
+    /// # Errors
+    /// Returns an error if the sample is earlier than the previous sample.
+    pub fn add_sample(&mut self, time: Duration, depth: Depth) -> Result<(), DiveComputerError> {
+        if time < self.time {
+            return Err(DiveComputerError::OutOfOrder);
+        }
+
+        let elapsed = time - self.time;
+        let current_depth = self.deco.model_depth();
+        let segment = if depth == current_depth {
+            Segment::new(
+                SegmentType::Bottom,
+                depth,
+                depth,
+                elapsed,
+                self.parameters.ascent_rate(),
+                self.parameters.descent_rate(),
+            )?
+        } else {
+            // Load the model at the rate between the samples, rather than the planned rates
+            let minutes = elapsed.as_seconds_f64() / 60.0;
+            let rate = if minutes > 0.0 {
+                Rate::from_metres_per_minute((depth.metres() - current_depth.metres()) / minutes)
+            } else {
+                Rate::default()
+            };
+            Segment::new(
+                SegmentType::AscDesc,
+                current_depth,
+                depth,
+                elapsed,
+                rate,
+                rate,
+            )?
+        };
+
+        self.deco =
+            self.deco
+                .clone()
+                .add_segment(&segment, &self.gas, self.parameters.environment())?;
+        self.time = time;
+
+        Ok(())
+    }
+
+    /// Switch to a new gas at `time` (from the start of the dive).
+    /// The diver is assumed to stay at the depth of the previous sample until the switch.
+    /// # Errors
+    /// Returns an error if the switch is earlier than the previous sample.
+    pub fn switch_gas(&mut self, time: Duration, gas: Gas) -> Result<(), DiveComputerError> {
+        self.add_sample(time, self.deco.model_depth())?;
+        self.gas = gas;
+
+        Ok(())
+    }
+
+    pub fn deco(&self) -> &T {
+        &self.deco
+    }
+
+    pub fn gas(&self) -> Gas {
+        self.gas
+    }
+
+    /// Time of the latest sample, from the start of the dive.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn depth(&self) -> Depth {
+        self.deco.model_depth()
+    }
+
+    /// Current ascent ceiling. The surface if no decompression is required.
+    pub fn ceiling(&self) -> Depth {
+        self.deco.ceiling_depth(self.parameters.environment())
+    }
+
+    /// Current GF99 (measured in %).
+    pub fn gf99(&self) -> f64 {
+        self.deco.gf99(self.parameters.environment())
+    }
+
+    /// Current SurfGF (measured in %).
+    pub fn surf_gf(&self) -> f64 {
+        self.deco.surf_gf(self.parameters.environment())
+    }
+
+    fn stops(&self) -> Result<Vec<Segment>, DecoError> {
+        self.deco.clone().get_stops(
+            self.parameters.ascent_rate(),
+            self.parameters.descent_rate(),
+            &self.gas,
+            self.parameters.environment(),
+        )
+    }
+
+    /// Remaining no-decompression time at the current depth on the current gas.
+    /// `None` if decompression is required.
+    /// # Errors
+    /// Returns an error if the model cannot calculate stops.
+    pub fn ndl(&self) -> Result<Option<Duration>, DecoError> {
+        Ok(self
+            .stops()?
+            .into_iter()
+            .find(|stop| stop.segment_type() == SegmentType::NoDeco)
+            .map(|stop| *stop.time()))
+    }
+
+    /// Next decompression stop on the current gas. `None` if no decompression is required.
+    /// # Errors
+    /// Returns an error if the model cannot calculate stops.
+    pub fn next_stop(&self) -> Result<Option<Segment>, DecoError> {
+        Ok(self
+            .stops()?
+            .into_iter()
+            .find(|stop| stop.segment_type() == SegmentType::DecoStop))
+    }
+
+    /// Time to surface on the current gas, including all stops and the final ascent.
+    /// # Errors
+    /// Returns an error if the model cannot calculate stops.
+    pub fn time_to_surface(&self) -> Result<Duration, DecoError> {
+        let stops = self
+            .stops()?
+            .into_iter()
+            .filter(|stop| stop.segment_type() != SegmentType::NoDeco)
+            .collect::<Vec<_>>();
+
+        let last_depth = stops
+            .last()
+            .map(|stop| stop.end_depth())
+            .unwrap_or_else(|| self.deco.model_depth());
+        let final_ascent = time_taken(self.parameters.ascent_rate(), last_depth, SURFACE);
+
+        Ok(stops
+            .iter()
+            .fold(final_ascent, |acc, stop| acc + *stop.time()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deco::zhl16::builder::ZHL16Builder;
+    use crate::deco::zhl16::ZHL16;
+    use crate::environment::Environment;
+    use crate::gas::GasModel;
+    use crate::limits::Limits;
+    use crate::units::altitude::Altitude;
+    use crate::units::consumption_rate::GasConsumptionRate;
+    use crate::units::water_density::SALTWATER;
+
+    fn parameters() -> Parameters {
+        Parameters::new(
+            Rate::from_metres_per_minute(-10.0),
+            Rate::from_metres_per_minute(20.0),
+            Environment::new(SALTWATER, Altitude(0)),
+            GasConsumptionRate::from_litres_per_minute(20.0),
+            GasConsumptionRate::from_litres_per_minute(15.0),
+            GasModel::Ideal,
+            Limits::default(),
+        )
+    }
+
+    /// The planner's model after descending to `metres` at the descent rate and staying there
+    /// until `minutes` into the dive.
+    fn planned(metres: f64, minutes: i64, gas: &Gas) -> ZHL16 {
+        let parameters = parameters();
+        let depth = Depth::from_metres(metres);
+        let descent_time = time_taken(parameters.descent_rate(), SURFACE, depth);
+        let descent = Segment::new(
+            SegmentType::AscDesc,
+            SURFACE,
+            depth,
+            descent_time,
+            parameters.ascent_rate(),
+            parameters.descent_rate(),
+        )
+        .unwrap();
+        let bottom = Segment::new(
+            SegmentType::Bottom,
+            depth,
+            depth,
+            Duration::minutes(minutes) - descent_time,
+            parameters.ascent_rate(),
+            parameters.descent_rate(),
+        )
+        .unwrap();
+
+        ZHL16Builder::new()
+            .finish()
+            .add_segment(&descent, gas, parameters.environment())
+            .unwrap()
+            .add_segment(&bottom, gas, parameters.environment())
+            .unwrap()
+    }
+
+    /// A dive computer fed the same profile as [`planned`], sampled every 10 seconds.
+    fn sampled(metres: f64, minutes: i64, gas: Gas) -> DiveComputer<ZHL16> {
+        let parameters = parameters();
+        let descent_rate = parameters.descent_rate().metres_per_minute();
+        let mut computer = DiveComputer::new(ZHL16Builder::new().finish(), gas, parameters);
+
+        for sample in 1..=minutes * 6 {
+            let time = Duration::seconds(sample * 10);
+            let depth = (time.as_seconds_f64() / 60.0 * descent_rate).min(metres);
+            computer
+                .add_sample(time, Depth::from_metres(depth))
+                .unwrap();
+        }
+        computer
+    }
+
+    fn planned_stops(deco: ZHL16, gas: &Gas) -> Vec<Segment> {
+        let parameters = parameters();
+        deco.get_stops(
+            parameters.ascent_rate(),
+            parameters.descent_rate(),
+            gas,
+            parameters.environment(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn sampled_ndl_matches_planner() {
+        let air = Gas::new(21, 0, 79).unwrap();
+        let stops = planned_stops(planned(30.0, 10, &air), &air);
+        let computer = sampled(30.0, 10, air);
+
+        assert_eq!(stops[0].segment_type(), SegmentType::NoDeco);
+        assert_eq!(computer.ndl().unwrap(), Some(*stops[0].time()));
+    }
+
+    #[test]
+    fn sampled_tts_matches_planner() {
+        let trimix = Gas::new(21, 35, 44).unwrap();
+        let stops = planned_stops(planned(45.0, 30, &trimix), &trimix);
+        let computer = sampled(45.0, 30, trimix);
+
+        let final_ascent = time_taken(
+            parameters().ascent_rate(),
+            stops.last().unwrap().end_depth(),
+            SURFACE,
+        );
+        let tts = stops
+            .iter()
+            .fold(final_ascent, |acc, stop| acc + *stop.time());
+
+        assert!(stops
+            .iter()
+            .any(|stop| stop.segment_type() == SegmentType::DecoStop));
+        assert_eq!(computer.time_to_surface().unwrap(), tts);
+        assert_eq!(
+            computer.next_stop().unwrap(),
+            stops
+                .into_iter()
+                .find(|stop| stop.segment_type() == SegmentType::DecoStop)
+        );
+    }
+}
@@ -1,26 +1,96 @@
-use crate::units::altitude::Altitude;
+use crate::units::altitude::{Altitude, AtmosphereModel};
+use crate::units::pressure::Pressure;
 use crate::units::water_density::WaterDensity;
 
+/// Source of the pressure at the surface of the water.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SurfacePressure {
+    /// Calculated from the altitude of the dive site.
+    Altitude(Altitude, AtmosphereModel),
+    /// Measured directly, such as by a dive computer before the dive.
+    Measured(Pressure),
+}
+
+impl SurfacePressure {
+    pub fn pressure(&self) -> Pressure {
+        match self {
+            SurfacePressure::Altitude(altitude, model) => model.pressure(*altitude),
+            SurfacePressure::Measured(pressure) => *pressure,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Environment {
     water_density: WaterDensity,
-    altitude: Altitude,
+    surface: SurfacePressure,
 }
 
 impl Environment {
+    /// Create an environment at an altitude, using the default atmosphere model.
     pub fn new(water_density: WaterDensity, altitude: Altitude) -> Self {
+        Self::with_atmosphere_model(water_density, altitude, AtmosphereModel::default())
+    }
+
+    /// Create an environment at an altitude, using the given atmosphere model.
+    pub fn with_atmosphere_model(
+        water_density: WaterDensity,
+        altitude: Altitude,
+        model: AtmosphereModel,
+    ) -> Self {
+        Self {
+            water_density,
+            surface: SurfacePressure::Altitude(altitude, model),
+        }
+    }
+
+    /// Create an environment with a measured surface pressure.
+    pub fn with_surface_pressure(water_density: WaterDensity, pressure: Pressure) -> Self {
         Self {
             water_density,
-            altitude,
+            surface: SurfacePressure::Measured(pressure),
         }
     }
 
+    /// The same environment at a different altitude, keeping the atmosphere model.
+    /// A measured surface pressure is replaced using the default atmosphere model.
+    pub fn at_altitude(&self, altitude: Altitude) -> Self {
+        let model = match self.surface {
+            SurfacePressure::Altitude(_, model) => model,
+            SurfacePressure::Measured(_) => AtmosphereModel::default(),
+        };
+        Self::with_atmosphere_model(self.water_density, altitude, model)
+    }
+
     pub fn water_density(&self) -> WaterDensity {
         self.water_density
     }
 
+    /// Altitude of the dive site. If the surface pressure was measured, this is the altitude at
+    /// which the default atmosphere model gives that pressure.
     pub fn altitude(&self) -> Altitude {
-        self.altitude
+        match self.surface {
+            SurfacePressure::Altitude(altitude, _) => altitude,
+            SurfacePressure::Measured(pressure) => AtmosphereModel::default().altitude(pressure),
+        }
+    }
+
+    /// Surface pressure measured at the dive site. `None` if it is calculated from the altitude.
+    pub fn measured_surface_pressure(&self) -> Option<Pressure> {
+        match self.surface {
+            SurfacePressure::Altitude(..) => None,
+            SurfacePressure::Measured(pressure) => Some(pressure),
+        }
+    }
+
+    pub fn surface(&self) -> SurfacePressure {
+        self.surface
+    }
+
+    /// Pressure at the surface of the water.
+    pub fn surface_pressure(&self) -> Pressure {
+        self.surface.pressure()
     }
 }
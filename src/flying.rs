@@ -0,0 +1,252 @@
+use crate::deco::zhl16::ZHL16;
+use crate::deco::{DecoAlgorithm, DecoError};
+use crate::environment::Environment;
+use crate::gas::Gas;
+use crate::plan::plan_result::PlanResult;
+use crate::segment::{Segment, SegmentType};
+use crate::units::depth::SURFACE;
+use crate::units::pressure::{Pressure, WATER_VAPOUR_PRESSURE};
+use crate::units::rate::Rate;
+use time::Duration;
+
+/// Typical minimum aircraft cabin pressure (equivalent to an altitude of about 2400 m).
+pub const CABIN_PRESSURE: Pressure = Pressure(0.75);
+
+/// Longest surface time searched when calculating desaturation and no-fly times (measured in minutes).
+const MAXIMUM_SURFACE_TIME: i64 = 7 * 24 * 60;
+
+/// Time until every compartment is within `tolerance` of equilibrium with air at the surface.
+/// Capped at seven days.
+/// # Arguments
+/// * `deco` - Decompression model at the end of the dive, with the diver at the surface
+/// * `tolerance` - Largest difference from equilibrium for each inert gas
+/// * `environment` - Environment the diver off-gasses in
+pub fn desaturation_time(
+    deco: ZHL16,
+    tolerance: Pressure,
+    environment: Environment,
+) -> Result<Duration, DecoError> {
+    let air = Gas::new(21, 0, 79).unwrap();
+    let equilibrium_n2 =
+        (environment.surface_pressure() - WATER_VAPOUR_PRESSURE) * Pressure(air.fr_n2());
+
+    surface_time(deco, environment, |deco| {
+        let tissue = deco.tissue();
+        tissue
+            .p_n2()
+            .iter()
+            .zip(tissue.p_he().iter())
+            .all(|(p_n2, p_he)| {
+                (p_n2.0 - equilibrium_n2.0).abs() <= tolerance.0 && p_he.0 <= tolerance.0
+            })
+    })
+}
+
+/// Time at the surface until the ascent ceiling is below `cabin_pressure`, so the diver can fly.
+/// The ceiling is calculated using the high gradient factor. Capped at seven days.
+/// # Arguments
+/// * `deco` - Decompression model at the end of the dive, with the diver at the surface
+/// * `cabin_pressure` - Lowest pressure the diver will be exposed to (usually [`CABIN_PRESSURE`])
+/// * `environment` - Environment the diver off-gasses in (usually at sea level)
+pub fn no_fly_time(
+    deco: ZHL16,
+    cabin_pressure: Pressure,
+    environment: Environment,
+) -> Result<Duration, DecoError> {
+    surface_time(deco, environment, |deco| {
+        deco.surface_ceiling() <= cabin_pressure
+    })
+}
+
+/// Off-gas the diver on air at the surface, a minute at a time, until `done` is satisfied.
+fn surface_time<F>(
+    mut deco: ZHL16,
+    environment: Environment,
+    done: F,
+) -> Result<Duration, DecoError>
+where
+    F: Fn(&ZHL16) -> bool,
+{
+    let air = Gas::new(21, 0, 79).unwrap();
+    let minute = Segment::new(
+        SegmentType::Bottom,
+        SURFACE,
+        SURFACE,
+        Duration::minute(),
+        Rate::default(),
+        Rate::default(),
+    )?;
+
+    for minutes in 0..MAXIMUM_SURFACE_TIME {
+        if done(&deco) {
+            return Ok(Duration::minutes(minutes));
+        }
+        deco = deco.add_segment(&minute, &air, environment)?;
+    }
+
+    Ok(Duration::minutes(MAXIMUM_SURFACE_TIME))
+}
+
+/// Minimum surface interval before flying, from the DAN flying after diving guidelines.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DanFlyingRule {
+    /// A single no-decompression dive: 12 hours.
+    SingleDive,
+    /// Multiple dives per day or multiple days of diving: 18 hours.
+    RepetitiveDives,
+    /// Dives requiring decompression stops: 24 hours.
+    DecompressionDives,
+}
+
+impl DanFlyingRule {
+    /// Find the rule which applies to a set of dives.
+    pub fn from_plans(plans: &[PlanResult]) -> Self {
+        let decompression = plans.iter().any(|plan| {
+            plan.segments()
+                .iter()
+                .any(|(segment, _)| segment.segment_type() == SegmentType::DecoStop)
+        });
+
+        if decompression {
+            DanFlyingRule::DecompressionDives
+        } else if plans.len() > 1 {
+            DanFlyingRule::RepetitiveDives
+        } else {
+            DanFlyingRule::SingleDive
+        }
+    }
+
+    pub fn minimum_interval(&self) -> Duration {
+        match self {
+            DanFlyingRule::SingleDive => Duration::hours(12),
+            DanFlyingRule::RepetitiveDives => Duration::hours(18),
+            DanFlyingRule::DecompressionDives => Duration::hours(24),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deco::zhl16::builder::ZHL16Builder;
+    use crate::units::altitude::Altitude;
+    use crate::units::depth::Depth;
+    use crate::units::water_density::SALTWATER;
+    use crate::util::time_taken;
+
+    fn environment() -> Environment {
+        Environment::new(SALTWATER, Altitude(0))
+    }
+
+    /// Dive to `metres` on air for `minutes`, perform any stops and surface.
+    fn dive(metres: f64, minutes: i64) -> (ZHL16, bool) {
+        let (ascent_rate, descent_rate) = (
+            Rate::from_metres_per_minute(-10.0),
+            Rate::from_metres_per_minute(20.0),
+        );
+        let air = Gas::new(21, 0, 79).unwrap();
+        let depth = Depth::from_metres(metres);
+        let descent = Segment::new(
+            SegmentType::AscDesc,
+            SURFACE,
+            depth,
+            time_taken(descent_rate, SURFACE, depth),
+            ascent_rate,
+            descent_rate,
+        )
+        .unwrap();
+        let bottom = Segment::new(
+            SegmentType::Bottom,
+            depth,
+            depth,
+            Duration::minutes(minutes),
+            ascent_rate,
+            descent_rate,
+        )
+        .unwrap();
+
+        let mut deco = ZHL16Builder::new()
+            .finish()
+            .add_segment(&descent, &air, environment())
+            .unwrap()
+            .add_segment(&bottom, &air, environment())
+            .unwrap();
+        let stops = deco
+            .get_stops(ascent_rate, descent_rate, &air, environment())
+            .unwrap()
+            .into_iter()
+            .filter(|stop| stop.segment_type() != SegmentType::NoDeco)
+            .collect::<Vec<_>>();
+        for stop in &stops {
+            deco = deco.add_segment(stop, &air, environment()).unwrap();
+        }
+        let ascent = Segment::new(
+            SegmentType::AscDesc,
+            deco.model_depth(),
+            SURFACE,
+            time_taken(ascent_rate, deco.model_depth(), SURFACE),
+            ascent_rate,
+            descent_rate,
+        )
+        .unwrap();
+
+        (
+            deco.add_segment(&ascent, &air, environment()).unwrap(),
+            !stops.is_empty(),
+        )
+    }
+
+    #[test]
+    fn no_fly_time_after_no_deco_dive() {
+        let (deco, decompression) = dive(18.0, 40);
+        let no_fly = no_fly_time(deco, CABIN_PRESSURE, environment()).unwrap();
+
+        assert!(!decompression);
+        assert!(no_fly < Duration::hours(12), "{:?}", no_fly);
+        assert!(no_fly <= desaturation_time(deco, Pressure(0.05), environment()).unwrap());
+    }
+
+    #[test]
+    fn no_fly_time_after_deco_dive() {
+        let (shallow, _) = dive(18.0, 40);
+        let (deep, decompression) = dive(30.0, 40);
+        let no_fly = no_fly_time(deep, CABIN_PRESSURE, environment()).unwrap();
+
+        assert!(decompression);
+        assert!(no_fly > Duration::zero());
+        assert!(no_fly > no_fly_time(shallow, CABIN_PRESSURE, environment()).unwrap());
+        assert!(no_fly < Duration::minutes(MAXIMUM_SURFACE_TIME));
+    }
+
+    #[test]
+    fn surface_ceiling_is_below_cabin_pressure_after_no_fly_time() {
+        let (deco, _) = dive(30.0, 40);
+        let no_fly = no_fly_time(deco, CABIN_PRESSURE, environment()).unwrap();
+        let air = Gas::new(21, 0, 79).unwrap();
+        let wait = |minutes| {
+            let segment = Segment::new(
+                SegmentType::Bottom,
+                SURFACE,
+                SURFACE,
+                minutes,
+                Rate::default(),
+                Rate::default(),
+            )
+            .unwrap();
+            deco.add_segment(&segment, &air, environment()).unwrap()
+        };
+
+        assert!(wait(no_fly).surface_ceiling() <= CABIN_PRESSURE);
+        assert!(wait(no_fly - Duration::minute()).surface_ceiling() > CABIN_PRESSURE);
+    }
+
+    #[test]
+    fn dan_rule_for_decompression_dives() {
+        assert_eq!(
+            DanFlyingRule::DecompressionDives.minimum_interval(),
+            Duration::hours(24)
+        );
+        assert_eq!(DanFlyingRule::from_plans(&[]), DanFlyingRule::SingleDive);
+    }
+}
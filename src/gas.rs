@@ -1,8 +1,33 @@
 use crate::environment::Environment;
 use crate::units::depth::Depth;
 use crate::units::pressure::Pressure;
+use std::fmt;
 use thiserror::Error;
 
+/// Virial coefficients of the compressibility factor of oxygen, for pressures in bar.
+const O2_VIRIAL_COEFFICIENTS: [f64; 3] = [-7.18092073703e-4, 2.81852572808e-6, -1.50290620492e-9];
+
+/// Virial coefficients of the compressibility factor of nitrogen, for pressures in bar.
+const N2_VIRIAL_COEFFICIENTS: [f64; 3] = [-2.19260353292e-4, 2.92844845532e-6, -2.07613482075e-9];
+
+/// Virial coefficients of the compressibility factor of helium, for pressures in bar.
+const HE_VIRIAL_COEFFICIENTS: [f64; 3] = [4.87320026468e-4, -8.83632921053e-8, 5.33304543646e-11];
+
+/// Density of oxygen at 0 °C and 1 atm (measured in g/L).
+pub const O2_DENSITY: f64 = 1.429;
+
+/// Density of nitrogen at 0 °C and 1 atm (measured in g/L).
+pub const N2_DENSITY: f64 = 1.2506;
+
+/// Density of helium at 0 °C and 1 atm (measured in g/L).
+pub const HE_DENSITY: f64 = 0.1786;
+
+/// Standard atmosphere, used as the reference pressure for gas densities (measured in bar).
+const STANDARD_PRESSURE: f64 = 1.01325;
+
+/// Number of iterations used to invert the real gas equation.
+const REAL_GAS_ITERATIONS: usize = 10;
+
 #[derive(Copy, Clone, Debug, Error, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum GasError {
     #[error("gas fractions do not add up to 100")]
@@ -51,7 +76,27 @@ impl Gas {
     }
 
     pub fn equivalent_narcotic_depth(&self, depth: Depth) -> Depth {
-        Depth(((depth.0 + 10) as f64 * (1.0 - self.fr_he()) - 10.0) as u32)
+        Depth::from_metres((depth.metres() + 10.0) * (1.0 - self.fr_he()) - 10.0)
+    }
+
+    /// Equivalent narcotic depth, treating only nitrogen as narcotic.
+    pub fn equivalent_narcotic_depth_without_o2(&self, depth: Depth) -> Depth {
+        Depth::from_metres((depth.metres() + 10.0) * self.fr_n2() / 0.79 - 10.0)
+    }
+
+    /// Density of the gas at a given depth (measured in g/L).
+    pub fn density(&self, depth: Depth, environment: Environment) -> f64 {
+        let surface_density =
+            self.fr_o2() * O2_DENSITY + self.fr_n2() * N2_DENSITY + self.fr_he() * HE_DENSITY;
+        surface_density * depth.pressure(environment).0 / STANDARD_PRESSURE
+    }
+
+    /// Whether switching from this gas to `to` increases the nitrogen fraction by more than
+    /// `ratio` times the decrease in the helium fraction (isobaric counterdiffusion).
+    pub fn violates_icd(&self, to: &Gas, ratio: f64) -> bool {
+        let n2_increase = to.fr_n2() - self.fr_n2();
+        let he_decrease = self.fr_he() - to.fr_he();
+        he_decrease > 0.0 && n2_increase > ratio * he_decrease
     }
 
     pub fn ppo2_in_range(
@@ -77,7 +122,124 @@ impl Gas {
         Pressure(depth.pressure(environment).0 * self.fr_n2())
     }
 
+    /// Exact fractions of O2, He and N2 breathed from a closed circuit loop using this gas as
+    /// diluent, with oxygen added to hold `setpoint`. The loop can be no leaner than the diluent
+    /// and no richer than pure oxygen.
+    pub fn loop_fractions(
+        &self,
+        setpoint: Pressure,
+        depth: Depth,
+        environment: Environment,
+    ) -> (f64, f64, f64) {
+        let inert = self.fr_he() + self.fr_n2();
+        if inert == 0.0 {
+            return (1.0, 0.0, 0.0);
+        }
+
+        let fr_o2 = (setpoint.0 / depth.pressure(environment).0)
+            .min(1.0)
+            .max(self.fr_o2());
+        let fr_inert = 1.0 - fr_o2;
+        (
+            fr_o2,
+            fr_inert * self.fr_he() / inert,
+            fr_inert * self.fr_n2() / inert,
+        )
+    }
+
     pub fn max_operating_depth(&self, max_pp_o2: Pressure, environment: Environment) -> Depth {
         Pressure(max_pp_o2.0 / self.fr_o2()).equivalent_depth(environment)
     }
+
+    /// Compressibility factor (Z) of the gas at a given pressure, at room temperature.
+    pub fn compressibility(&self, pressure: Pressure) -> f64 {
+        mix_compressibility(self.fr_o2(), self.fr_he(), self.fr_n2(), pressure)
+    }
+}
+
+/// Common name of the gas: `Air`, `O2`, `EAN32` for nitrox, or `Tx18/45` for trimix and heliox.
+impl fmt::Display for Gas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.o2, self.he) {
+            (21, 0) => write!(f, "Air"),
+            (100, 0) => write!(f, "O2"),
+            (o2, 0) => write!(f, "EAN{}", o2),
+            (o2, he) => write!(f, "Tx{}/{}", o2, he),
+        }
+    }
+}
+
+/// Compressibility factor of a mix, calculated as the mole-fraction weighted sum
+/// of a cubic virial fit for each component.
+fn mix_compressibility(fr_o2: f64, fr_he: f64, fr_n2: f64, pressure: Pressure) -> f64 {
+    let virial = |coefficients: [f64; 3]| {
+        let [a, b, c] = coefficients;
+        pressure.0 * (a + pressure.0 * (b + pressure.0 * c))
+    };
+
+    1.0 + fr_o2 * virial(O2_VIRIAL_COEFFICIENTS)
+        + fr_n2 * virial(N2_VIRIAL_COEFFICIENTS)
+        + fr_he * virial(HE_VIRIAL_COEFFICIENTS)
+}
+
+/// Equation of state used to convert between cylinder pressure and free gas volume.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GasModel {
+    /// Ideal gas law (Boyle's law).
+    #[default]
+    Ideal,
+    /// Real gas, corrected by the compressibility factor of the mix.
+    Real,
+}
+
+impl GasModel {
+    pub fn compressibility(&self, gas: &Gas, pressure: Pressure) -> f64 {
+        match self {
+            GasModel::Ideal => 1.0,
+            GasModel::Real => gas.compressibility(pressure),
+        }
+    }
+
+    /// Free volume of gas (measured in litres at surface pressure) held in a container.
+    /// # Arguments
+    /// * `gas` - Gas in the container
+    /// * `water_volume` - Internal volume of the container (measured in litres)
+    /// * `pressure` - Pressure of the gas in the container
+    pub fn volume(&self, gas: &Gas, water_volume: f64, pressure: Pressure) -> f64 {
+        water_volume * pressure.0 / self.compressibility(gas, pressure)
+    }
+
+    /// Pressure needed to hold a free volume of gas (measured in litres at surface pressure)
+    /// in a container. Inverse of [`GasModel::volume`].
+    pub fn pressure(&self, gas: &Gas, water_volume: f64, volume: f64) -> Pressure {
+        self.mix_pressure(
+            [
+                volume * gas.fr_o2(),
+                volume * gas.fr_he(),
+                volume * gas.fr_n2(),
+            ],
+            water_volume,
+        )
+    }
+
+    /// Pressure needed to hold free volumes of oxygen, helium and nitrogen (in that order)
+    /// in a container. Unlike [`GasModel::pressure`], the mix is not limited to whole percentages.
+    pub(crate) fn mix_pressure(&self, volumes: [f64; 3], water_volume: f64) -> Pressure {
+        let volume = volumes.iter().sum::<f64>();
+        let ideal = Pressure(volume / water_volume);
+        if volume == 0.0 {
+            return ideal;
+        }
+
+        let [o2, he, n2] = volumes;
+        match self {
+            GasModel::Ideal => ideal,
+            GasModel::Real => (0..REAL_GAS_ITERATIONS).fold(ideal, |pressure, _| {
+                Pressure(
+                    ideal.0 * mix_compressibility(o2 / volume, he / volume, n2 / volume, pressure),
+                )
+            }),
+        }
+    }
 }
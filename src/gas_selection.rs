@@ -0,0 +1,147 @@
+use crate::environment::Environment;
+use crate::gas::Gas;
+use crate::units::depth::Depth;
+use crate::units::pressure::{Pressure, PPO2_FUDGE_FACTOR};
+
+/// Interval between decompression stops, used to round gas switch depths (measured in metres).
+const STOP_INTERVAL: f64 = 3.0;
+
+/// Standard decompression gases, with the shallowest planned depth for which each is recommended
+/// (measured in metres).
+const STANDARD_DECO_GASES: [((u8, u8, u8), f64); 3] = [
+    ((100, 0, 0), 6.0),
+    ((50, 0, 50), 21.0),
+    ((35, 25, 40), 55.0),
+];
+
+/// Find the best mix for a given depth: the most oxygen allowed by the ppO2 limit,
+/// then the most nitrogen allowed by the END limit (and density limit, if given).
+/// Returns `None` if no mix satisfies all limits.
+/// # Arguments
+/// * `depth` - Maximum depth the gas will be breathed at
+/// * `max_pp_o2` - Maximum ppO2 at that depth
+/// * `max_end` - Maximum equivalent narcotic depth
+/// * `o2_narcotic` - Whether oxygen is considered narcotic when calculating END
+/// * `max_density` - Maximum gas density at that depth (measured in g/L)
+/// * `environment` - Dive environment
+pub fn best_mix(
+    depth: Depth,
+    max_pp_o2: Pressure,
+    max_end: Depth,
+    o2_narcotic: bool,
+    max_density: Option<f64>,
+    environment: Environment,
+) -> Option<Gas> {
+    let fr_o2 = (max_pp_o2.0 / depth.pressure(environment).0).min(1.0);
+    let o2 = (fr_o2 * 100.0).floor() as u8;
+    if o2 == 0 {
+        return None;
+    }
+
+    (0..=100 - o2).rev().find_map(|n2| {
+        let gas = Gas::new(o2, 100 - o2 - n2, n2).unwrap();
+        let end = if o2_narcotic {
+            gas.equivalent_narcotic_depth(depth)
+        } else {
+            gas.equivalent_narcotic_depth_without_o2(depth)
+        };
+        let dense = max_density
+            .map(|max_density| gas.density(depth, environment) > max_density)
+            .unwrap_or(false);
+
+        if end <= max_end && !dense {
+            Some(gas)
+        } else {
+            None
+        }
+    })
+}
+
+/// Recommended decompression gases for a dive to a planned depth, with their switch depths.
+/// Oxygen is recommended below 6 m, EAN50 below 21 m and trimix 35/25 below 55 m.
+/// Switch depths are the deepest stop within the MOD of the gas at `max_pp_o2`,
+/// allowing the same fudge factor as the planner.
+/// The result can be passed to the open circuit planner as its deco gases.
+pub fn deco_gas_set(
+    depth: Depth,
+    max_pp_o2: Pressure,
+    environment: Environment,
+) -> Vec<(Gas, Option<Depth>)> {
+    STANDARD_DECO_GASES
+        .iter()
+        .filter(|(_, min_depth)| depth.metres() > *min_depth)
+        .map(|((o2, he, n2), _)| {
+            let gas = Gas::new(*o2, *he, *n2).unwrap();
+            let mod_metres = gas
+                .max_operating_depth(max_pp_o2 + PPO2_FUDGE_FACTOR, environment)
+                .metres();
+            let switch_depth = (mod_metres / STOP_INTERVAL).floor() * STOP_INTERVAL;
+            (gas, Some(Depth::from_metres(switch_depth)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::altitude::Altitude;
+    use crate::units::water_density::SALTWATER;
+
+    fn environment() -> Environment {
+        Environment::new(SALTWATER, Altitude(0))
+    }
+
+    fn best_60m_mix(o2_narcotic: bool) -> Option<Gas> {
+        best_mix(
+            Depth::from_metres(60.0),
+            Pressure(1.4),
+            Depth::from_metres(30.0),
+            o2_narcotic,
+            None,
+            environment(),
+        )
+    }
+
+    #[test]
+    fn best_mix_with_oxygen_narcotic() {
+        // 19% O2 keeps ppO2 below 1.4 bar at 7.15 bar, and 43% He gives an END of 29.9 m
+        assert_eq!(best_60m_mix(true), Some(Gas::new(19, 43, 38).unwrap()));
+    }
+
+    #[test]
+    fn best_mix_with_only_nitrogen_narcotic() {
+        // With only nitrogen narcotic, 45% N2 gives an END of 29.9 m
+        assert_eq!(best_60m_mix(false), Some(Gas::new(19, 36, 45).unwrap()));
+    }
+
+    #[test]
+    fn best_mix_respects_density_limit() {
+        let gas = best_mix(
+            Depth::from_metres(60.0),
+            Pressure(1.4),
+            Depth::from_metres(30.0),
+            false,
+            Some(5.2),
+            environment(),
+        )
+        .unwrap();
+
+        assert!(gas.density(Depth::from_metres(60.0), environment()) <= 5.2);
+        assert!(gas.he() > 36);
+    }
+
+    #[test]
+    fn deco_gases_are_chosen_by_depth() {
+        let gases = |depth: f64| {
+            deco_gas_set(Depth::from_metres(depth), Pressure(1.6), environment())
+                .into_iter()
+                .map(|(gas, switch)| (gas.o2(), switch.unwrap().metres()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(gases(6.0), vec![]);
+        assert_eq!(gases(18.0), vec![(100, 6.0)]);
+        assert_eq!(gases(30.0), vec![(100, 6.0), (50, 21.0)]);
+        assert_eq!(gases(60.0), vec![(100, 6.0), (50, 21.0), (35, 36.0)]);
+    }
+}
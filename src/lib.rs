@@ -1,7 +1,17 @@
+pub mod altitude_exposure;
+pub mod analysis;
+pub mod blending;
+pub mod cylinder;
+pub mod dive_computer;
+pub mod flying;
 pub mod gas;
+pub mod gas_selection;
+pub mod oxygen;
 pub mod segment;
+pub mod surface_interval;
 pub mod tissue;
 
+pub mod limits;
 pub mod parameter;
 pub mod units;
 pub mod util;
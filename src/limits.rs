@@ -0,0 +1,68 @@
+use crate::units::depth::Depth;
+
+/// Recommended maximum gas density (measured in g/L).
+pub const DENSITY_RECOMMENDED: f64 = 5.2;
+
+/// Absolute maximum gas density (measured in g/L).
+pub const DENSITY_MAXIMUM: f64 = 6.2;
+
+/// Default maximum equivalent narcotic depth.
+pub const END_MAXIMUM: Depth = Depth::from_millimetres(30_000);
+
+/// Rule of fifths: on a gas switch, the increase in nitrogen should not exceed a fifth of the
+/// decrease in helium, to avoid isobaric counterdiffusion.
+pub const ICD_RULE_OF_FIFTHS: f64 = 0.2;
+
+/// Safety limits applied when planning a dive.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Limits {
+    recommended_density: f64,
+    maximum_density: f64,
+    icd_limit: Option<f64>,
+    max_end: Depth,
+}
+
+impl Limits {
+    /// # Arguments
+    /// * `recommended_density` - Gas density above which segments are reported (measured in g/L)
+    /// * `maximum_density` - Gas density above which a gas may not be breathed (measured in g/L)
+    /// * `icd_limit` - If set, gas switches where the nitrogen increase exceeds this ratio
+    ///   of the helium decrease are not used, and any left in the plan are reported
+    /// * `max_end` - Equivalent narcotic depth above which segments are reported
+    pub fn new(
+        recommended_density: f64,
+        maximum_density: f64,
+        icd_limit: Option<f64>,
+        max_end: Depth,
+    ) -> Self {
+        Self {
+            recommended_density,
+            maximum_density,
+            icd_limit,
+            max_end,
+        }
+    }
+
+    pub fn recommended_density(&self) -> f64 {
+        self.recommended_density
+    }
+
+    pub fn maximum_density(&self) -> f64 {
+        self.maximum_density
+    }
+
+    pub fn icd_limit(&self) -> Option<f64> {
+        self.icd_limit
+    }
+
+    pub fn max_end(&self) -> Depth {
+        self.max_end
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::new(DENSITY_RECOMMENDED, DENSITY_MAXIMUM, None, END_MAXIMUM)
+    }
+}
@@ -0,0 +1,235 @@
+use crate::environment::Environment;
+use crate::gas::Gas;
+use crate::segment::{Segment, SegmentType};
+use crate::units::depth::Depth;
+use crate::units::pressure::Pressure;
+use std::ops::{Add, AddAssign};
+use time::Duration;
+
+/// NOAA single dive exposure limits, as (ppO2, minutes).
+pub const NOAA_SINGLE_DIVE_LIMITS: [(f64, f64); 11] = [
+    (0.6, 720.0),
+    (0.7, 570.0),
+    (0.8, 450.0),
+    (0.9, 360.0),
+    (1.0, 300.0),
+    (1.1, 240.0),
+    (1.2, 210.0),
+    (1.3, 180.0),
+    (1.4, 150.0),
+    (1.5, 120.0),
+    (1.6, 45.0),
+];
+
+/// ppO2 below which no oxygen toxicity is accumulated.
+pub const PPO2_TOXICITY_THRESHOLD: Pressure = Pressure(0.5);
+
+/// Half-time of CNS toxicity elimination at the surface (measured in minutes).
+pub const CNS_HALF_TIME: f64 = 90.0;
+
+/// Step used to integrate exposure over segments with changing depth (measured in seconds).
+const INTEGRATION_STEP: f64 = 10.0;
+
+/// Oxygen toxicity accumulated by the diver.
+#[derive(Copy, Clone, Debug, Default, PartialOrd, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OxygenExposure {
+    cns: f64,
+    otu: f64,
+}
+
+impl OxygenExposure {
+    pub fn new(cns: f64, otu: f64) -> Self {
+        Self { cns, otu }
+    }
+
+    /// CNS toxicity as a percentage of the NOAA single dive limit.
+    pub fn cns(&self) -> f64 {
+        self.cns
+    }
+
+    /// Pulmonary toxicity in oxygen tolerance units (equivalent to UPTD).
+    pub fn otu(&self) -> f64 {
+        self.otu
+    }
+
+    /// CNS percentage accumulated per minute at a given ppO2.
+    /// Above 1.6 bar, the rate is extrapolated linearly from the last interval of the NOAA table.
+    pub fn cns_per_minute(pp_o2: Pressure) -> f64 {
+        let (first, _) = NOAA_SINGLE_DIVE_LIMITS[0];
+        if pp_o2 <= PPO2_TOXICITY_THRESHOLD {
+            return 0.0;
+        }
+        if pp_o2.0 <= first {
+            return 100.0 / NOAA_SINGLE_DIVE_LIMITS[0].1;
+        }
+
+        for window in NOAA_SINGLE_DIVE_LIMITS.windows(2) {
+            let ((low_pp, low_limit), (high_pp, high_limit)) = (window[0], window[1]);
+            if pp_o2.0 <= high_pp {
+                let limit =
+                    low_limit + (high_limit - low_limit) * (pp_o2.0 - low_pp) / (high_pp - low_pp);
+                return 100.0 / limit;
+            }
+        }
+
+        let (low_pp, low_limit) = NOAA_SINGLE_DIVE_LIMITS[NOAA_SINGLE_DIVE_LIMITS.len() - 2];
+        let (high_pp, high_limit) = NOAA_SINGLE_DIVE_LIMITS[NOAA_SINGLE_DIVE_LIMITS.len() - 1];
+        let slope = (100.0 / high_limit - 100.0 / low_limit) / (high_pp - low_pp);
+        100.0 / high_limit + slope * (pp_o2.0 - high_pp)
+    }
+
+    /// OTUs accumulated per minute at a given ppO2.
+    pub fn otu_per_minute(pp_o2: Pressure) -> f64 {
+        if pp_o2 <= PPO2_TOXICITY_THRESHOLD {
+            return 0.0;
+        }
+        ((pp_o2 - PPO2_TOXICITY_THRESHOLD).0 / PPO2_TOXICITY_THRESHOLD.0).powf(5.0 / 6.0)
+    }
+
+    /// Exposure from breathing a gas over a segment.
+    /// On ascents and descents the ppO2 is integrated along the depth change.
+    pub fn from_segment(segment: &Segment, gas: &Gas, environment: Environment) -> Self {
+        OxygenExposure::integrate(segment, |depth| gas.pp_o2(depth, environment))
+    }
+
+    /// Exposure from breathing a closed circuit loop held at `setpoint` over a segment.
+    /// Where the ambient pressure is below the setpoint, the loop is pure oxygen at ambient
+    /// pressure; where the diluent alone exceeds the setpoint, the diluent's ppO2 is breathed.
+    pub fn from_loop_segment(
+        segment: &Segment,
+        diluent: &Gas,
+        setpoint: Pressure,
+        environment: Environment,
+    ) -> Self {
+        OxygenExposure::integrate(segment, |depth| {
+            let (fr_o2, _, _) = diluent.loop_fractions(setpoint, depth, environment);
+            Pressure(depth.pressure(environment).0 * fr_o2)
+        })
+    }
+
+    fn integrate(segment: &Segment, pp_o2: impl Fn(Depth) -> Pressure) -> Self {
+        let minutes = segment.time().as_seconds_f64() / 60.0;
+        match segment.segment_type() {
+            SegmentType::NoDeco => Self::default(),
+            SegmentType::AscDesc => {
+                let start = segment.start_depth().metres();
+                let end = segment.end_depth().metres();
+                let steps = (segment.time().as_seconds_f64() / INTEGRATION_STEP)
+                    .ceil()
+                    .max(1.0);
+
+                (0..steps as u32)
+                    .map(|step| {
+                        let depth = start + (end - start) * (step as f64 + 0.5) / steps;
+                        let pp_o2 = pp_o2(Depth::from_metres(depth));
+                        Self::new(
+                            OxygenExposure::cns_per_minute(pp_o2) * minutes / steps,
+                            OxygenExposure::otu_per_minute(pp_o2) * minutes / steps,
+                        )
+                    })
+                    .fold(Self::default(), |acc, exposure| acc + exposure)
+            }
+            _ => {
+                let pp_o2 = pp_o2(segment.end_depth());
+                Self::new(
+                    OxygenExposure::cns_per_minute(pp_o2) * minutes,
+                    OxygenExposure::otu_per_minute(pp_o2) * minutes,
+                )
+            }
+        }
+    }
+
+    /// Exposure carried over to a repetitive dive after a surface interval.
+    /// CNS decays with a 90 minute half-time, while OTUs accumulate over the day.
+    pub fn after_surface_interval(&self, interval: Duration) -> Self {
+        let minutes = interval.as_seconds_f64() / 60.0;
+        Self {
+            cns: self.cns * 0.5_f64.powf(minutes / CNS_HALF_TIME),
+            otu: self.otu,
+        }
+    }
+}
+
+impl Add for OxygenExposure {
+    type Output = OxygenExposure;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        OxygenExposure {
+            cns: self.cns + rhs.cns,
+            otu: self.otu + rhs.otu,
+        }
+    }
+}
+
+impl AddAssign for OxygenExposure {
+    fn add_assign(&mut self, rhs: Self) {
+        self.cns += rhs.cns;
+        self.otu += rhs.otu;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::altitude::Altitude;
+    use crate::units::rate::Rate;
+    use crate::units::water_density::SALTWATER;
+
+    #[test]
+    fn noaa_limit_is_100_percent_cns() {
+        let cns = OxygenExposure::cns_per_minute(Pressure(1.4)) * 150.0;
+        assert!((cns - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cns_is_interpolated_between_table_entries() {
+        // Halfway between 150 minutes at 1.4 bar and 120 minutes at 1.5 bar
+        let cns = OxygenExposure::cns_per_minute(Pressure(1.45));
+        assert!((cns - 100.0 / 135.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn no_toxicity_below_threshold() {
+        assert_eq!(OxygenExposure::cns_per_minute(Pressure(0.45)), 0.0);
+        assert_eq!(OxygenExposure::otu_per_minute(Pressure(0.45)), 0.0);
+        assert_eq!(OxygenExposure::otu_per_minute(PPO2_TOXICITY_THRESHOLD), 0.0);
+    }
+
+    #[test]
+    fn otu_formula() {
+        assert!((OxygenExposure::otu_per_minute(Pressure(1.0)) - 1.0).abs() < 1e-9);
+        assert!(
+            (OxygenExposure::otu_per_minute(Pressure(1.5)) - 2.0_f64.powf(5.0 / 6.0)).abs() < 1e-9
+        );
+    }
+
+    #[test]
+    fn flat_segment_exposure() {
+        let environment = Environment::new(SALTWATER, Altitude(0));
+        let oxygen = Gas::new(100, 0, 0).unwrap();
+        let depth = Depth::from_metres(6.0);
+        let segment = Segment::new(
+            SegmentType::DecoStop,
+            depth,
+            depth,
+            Duration::minutes(30),
+            Rate::from_metres_per_minute(-10.0),
+            Rate::from_metres_per_minute(20.0),
+        )
+        .unwrap();
+
+        let pp_o2 = oxygen.pp_o2(depth, environment);
+        let exposure = OxygenExposure::from_segment(&segment, &oxygen, environment);
+        assert!((exposure.cns() - OxygenExposure::cns_per_minute(pp_o2) * 30.0).abs() < 1e-9);
+        assert!((exposure.otu() - OxygenExposure::otu_per_minute(pp_o2) * 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cns_halves_every_90_minutes_at_the_surface() {
+        let exposure =
+            OxygenExposure::new(80.0, 200.0).after_surface_interval(Duration::minutes(90));
+        assert!((exposure.cns() - 40.0).abs() < 1e-9);
+        assert_eq!(exposure.otu(), 200.0);
+    }
+}
@@ -1,4 +1,6 @@
 use crate::environment::Environment;
+use crate::gas::GasModel;
+use crate::limits::Limits;
 use crate::units::consumption_rate::GasConsumptionRate;
 use crate::units::rate::Rate;
 
@@ -10,6 +12,8 @@ pub struct Parameters {
     environment: Environment,
     sac_bottom: GasConsumptionRate,
     sac_deco: GasConsumptionRate,
+    gas_model: GasModel,
+    limits: Limits,
 }
 
 impl Parameters {
@@ -19,6 +23,8 @@ impl Parameters {
         environment: Environment,
         sac_bottom: GasConsumptionRate,
         sac_deco: GasConsumptionRate,
+        gas_model: GasModel,
+        limits: Limits,
     ) -> Self {
         Parameters {
             ascent_rate,
@@ -26,6 +32,8 @@ impl Parameters {
             environment,
             sac_bottom,
             sac_deco,
+            gas_model,
+            limits,
         }
     }
 }
@@ -46,4 +54,10 @@ impl Parameters {
     pub fn sac_deco(&self) -> GasConsumptionRate {
         self.sac_deco
     }
+    pub fn gas_model(&self) -> GasModel {
+        self.gas_model
+    }
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
 }
@@ -0,0 +1,443 @@
+use crate::cylinder::{Cylinder, CylinderUsage};
+use crate::deco::DecoAlgorithm;
+use crate::gas::Gas;
+use crate::oxygen::OxygenExposure;
+use crate::parameter::Parameters;
+use crate::plan::closed_circuit::setpoint::Setpoints;
+use crate::plan::plan_result::PlanResult;
+use crate::plan::{DivePlan, PlanError};
+use crate::segment::{Segment, SegmentType};
+use crate::units::consumption::GasConsumption;
+use crate::units::consumption_rate::GasConsumptionRate;
+use crate::units::depth::{Depth, SURFACE};
+use crate::units::pressure::Pressure;
+use crate::util::time_taken;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+pub mod setpoint;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClosedCircuit<T>
+where
+    T: DecoAlgorithm + Clone,
+{
+    deco: T,
+    bottom_segments: Vec<Segment>,
+    diluent: Gas,
+    setpoints: Setpoints,
+    loop_volume: GasConsumption,
+    o2_consumption: GasConsumptionRate,
+    parameters: Parameters,
+    cylinders: Vec<Cylinder>,
+}
+
+impl<T> ClosedCircuit<T>
+where
+    T: DecoAlgorithm + Clone,
+{
+    /// Create a new closed circuit plan.
+    /// # Arguments
+    /// * `deco` - Decompression algorithm to use
+    /// * `bottom_segments` - Segments of the dive, all breathed on the loop
+    /// * `diluent` - Diluent used by the rebreather
+    /// * `setpoints` - ppO2 setpoints for the descent, bottom and decompression phases
+    /// * `loop_volume` - Volume of the breathing loop (measured in litres)
+    /// * `o2_consumption` - Metabolic oxygen consumption (measured in litres per minute)
+    /// * `parameters` - Dive parameters. SAC rates are not used for closed circuit dives.
+    pub fn new(
+        deco: T,
+        bottom_segments: &[Segment],
+        diluent: Gas,
+        setpoints: Setpoints,
+        loop_volume: GasConsumption,
+        o2_consumption: GasConsumptionRate,
+        parameters: Parameters,
+    ) -> Self {
+        Self {
+            deco,
+            bottom_segments: bottom_segments.to_vec(),
+            diluent,
+            setpoints,
+            loop_volume,
+            o2_consumption,
+            parameters,
+            cylinders: Vec::new(),
+        }
+    }
+
+    /// Cylinders carried for the diluent and oxygen, used to report remaining gas. Oxygen is
+    /// drawn from cylinders holding pure oxygen.
+    pub fn with_cylinders(mut self, cylinders: &[Cylinder]) -> Self {
+        self.cylinders = cylinders.to_vec();
+        self
+    }
+
+    /// Equivalent open circuit gas breathed from the loop at a given depth and setpoint,
+    /// rounded to whole percentages. Only used to report the plan and to estimate stops: the
+    /// model itself is loaded with the exact loop fractions.
+    fn loop_gas(&self, setpoint: Pressure, depth: Depth) -> Gas {
+        let (fr_o2, fr_he, _) =
+            self.diluent
+                .loop_fractions(setpoint, depth, self.parameters.environment());
+        let o2 = (fr_o2 * 100.0).round() as u8;
+        let he = ((fr_he * 100.0).round() as u8).min(100 - o2);
+        Gas::new(o2, he, 100 - o2 - he).unwrap()
+    }
+
+    /// Equivalent open circuit gas breathed over a whole segment.
+    fn segment_gas(&self, setpoint: Pressure, segment: &Segment) -> Gas {
+        let depth = match segment.segment_type() {
+            SegmentType::AscDesc => (segment.start_depth() + segment.end_depth()) / 2,
+            _ => segment.end_depth(),
+        };
+        self.loop_gas(setpoint, depth)
+    }
+
+    fn add_segment(
+        &self,
+        running_model: T,
+        segment: Segment,
+        setpoint: Pressure,
+        segments: &mut Vec<(Segment, Pressure)>,
+    ) -> Result<T, PlanError> {
+        segments.push((segment, setpoint));
+        Ok(running_model.add_loop_segment(
+            &segment,
+            &self.diluent,
+            setpoint,
+            self.parameters.environment(),
+        )?)
+    }
+
+    fn depth_change(&self, start: Depth, end: Depth) -> Result<Segment, PlanError> {
+        Segment::new(
+            SegmentType::AscDesc,
+            start,
+            end,
+            time_taken(
+                if start < end {
+                    self.parameters.descent_rate()
+                } else {
+                    self.parameters.ascent_rate()
+                },
+                start,
+                end,
+            ),
+            self.parameters.ascent_rate(),
+            self.parameters.descent_rate(),
+        )
+        .map_err(PlanError::from)
+    }
+
+    /// Ascend to `target`, performing any required stops on the given setpoint.
+    /// The loop gas is recalculated at every stop, as its oxygen fraction rises during the ascent.
+    fn ascend(
+        &self,
+        mut running_model: T,
+        target: Depth,
+        setpoint: Pressure,
+        segments: &mut Vec<(Segment, Pressure)>,
+    ) -> Result<T, PlanError> {
+        let mut stopped = false;
+
+        loop {
+            let depth = running_model.model_depth();
+            let stops = running_model.clone().get_stops(
+                self.parameters.ascent_rate(),
+                self.parameters.descent_rate(),
+                &self.loop_gas(setpoint, depth),
+                self.parameters.environment(),
+            )?;
+
+            let next_stop = stops.iter().find(|stop| {
+                stop.segment_type() == SegmentType::DecoStop && stop.start_depth() > target
+            });
+
+            let stop_depth = match next_stop {
+                Some(stop) => stop.start_depth(),
+                None => {
+                    if target == SURFACE && !stopped {
+                        // No decompression needed: record the NDL as the open circuit plan does.
+                        if let Some(ndl) = stops
+                            .into_iter()
+                            .find(|stop| stop.segment_type() == SegmentType::NoDeco)
+                        {
+                            segments.push((ndl, setpoint));
+                        }
+                    } else if target > SURFACE && depth > target {
+                        running_model = self.add_segment(
+                            running_model,
+                            self.depth_change(depth, target)?,
+                            setpoint,
+                            segments,
+                        )?;
+                    }
+                    break Ok(running_model);
+                }
+            };
+
+            if stop_depth != depth {
+                running_model = self.add_segment(
+                    running_model,
+                    self.depth_change(depth, stop_depth)?,
+                    setpoint,
+                    segments,
+                )?;
+            }
+
+            // At the stop, use the loop gas for this depth to calculate the stop time
+            let stop = running_model
+                .clone()
+                .get_stops(
+                    self.parameters.ascent_rate(),
+                    self.parameters.descent_rate(),
+                    &self.loop_gas(setpoint, stop_depth),
+                    self.parameters.environment(),
+                )?
+                .into_iter()
+                .find(|stop| {
+                    stop.segment_type() == SegmentType::DecoStop && stop.start_depth() == stop_depth
+                });
+
+            if let Some(stop) = stop {
+                running_model = self.add_segment(running_model, stop, setpoint, segments)?;
+                stopped = true;
+            }
+        }
+    }
+
+    fn travel(
+        &self,
+        running_model: T,
+        target: Depth,
+        segments: &mut Vec<(Segment, Pressure)>,
+    ) -> Result<T, PlanError> {
+        let depth = running_model.model_depth();
+        match depth.cmp(&target) {
+            Ordering::Less => self.add_segment(
+                running_model,
+                self.depth_change(depth, target)?,
+                self.setpoints.descent(),
+                segments,
+            ),
+            Ordering::Equal => Ok(running_model),
+            Ordering::Greater => {
+                self.ascend(running_model, target, self.setpoints.bottom(), segments)
+            }
+        }
+    }
+
+    /// Diluent and oxygen used by a segment. They are returned separately, as an oxygen
+    /// diluent is carried in its own cylinder.
+    fn gas_used(&self, segment: &Segment) -> (GasConsumption, GasConsumption) {
+        if segment.segment_type() == SegmentType::NoDeco {
+            return (GasConsumption(0), GasConsumption(0));
+        }
+
+        // Diluent is added to keep the loop volume constant as ambient pressure increases, and
+        // to flush the loop as it is vented on the way up.
+        let environment = self.parameters.environment();
+        let pressure_change = (segment.end_depth().pressure(environment)
+            - segment.start_depth().pressure(environment))
+        .0
+        .abs();
+        let diluent = self.loop_volume.0 as f64 * pressure_change;
+        let oxygen =
+            segment.time().as_seconds_f64() / 60.0 * self.o2_consumption.litres_per_minute();
+
+        (
+            GasConsumption(diluent.round() as u32),
+            GasConsumption(oxygen.round() as u32),
+        )
+    }
+}
+
+impl<T> DivePlan for ClosedCircuit<T>
+where
+    T: DecoAlgorithm + Clone,
+{
+    fn get_plan(mut self) -> Result<PlanResult, PlanError> {
+        let mut segments: Vec<(Segment, Pressure)> = Vec::new();
+
+        for segment in self.bottom_segments.clone() {
+            // Link up the diver's depth with the start of the segment
+            self.deco = self.travel(self.deco.clone(), segment.start_depth(), &mut segments)?;
+
+            let setpoint = match segment.segment_type() {
+                SegmentType::AscDesc if segment.end_depth() > segment.start_depth() => {
+                    self.setpoints.descent()
+                }
+                _ => self.setpoints.bottom(),
+            };
+            self.deco = self.add_segment(self.deco.clone(), segment, setpoint, &mut segments)?;
+        }
+
+        self.deco = self.ascend(
+            self.deco.clone(),
+            SURFACE,
+            self.setpoints.deco(),
+            &mut segments,
+        )?;
+
+        let environment = self.parameters.environment();
+        let oxygen = Gas::new(100, 0, 0).unwrap();
+        let mut segment_gas_used = Vec::new();
+        let mut diluent_used = GasConsumption::default();
+        let mut oxygen_used = GasConsumption::default();
+        for (index, (segment, _)) in segments.iter().enumerate() {
+            let (diluent, o2) = self.gas_used(segment);
+            segment_gas_used.push((index, self.diluent, diluent));
+            segment_gas_used.push((index, oxygen, o2));
+            diluent_used += diluent;
+            oxygen_used += o2;
+        }
+
+        let mut gas_plan = HashMap::new();
+        gas_plan.insert(self.diluent, diluent_used);
+        let (cylinders, gas_shortfalls) = CylinderUsage::drain(
+            &self.cylinders,
+            &segment_gas_used,
+            self.parameters.gas_model(),
+        );
+
+        let oxygen_exposure = segments
+            .iter()
+            .map(|(segment, setpoint)| {
+                OxygenExposure::from_loop_segment(segment, &self.diluent, *setpoint, environment)
+            })
+            .collect::<Vec<_>>();
+        let segments = segments
+            .iter()
+            .map(|(segment, setpoint)| (*segment, self.segment_gas(*setpoint, segment)))
+            .collect::<Vec<_>>();
+
+        Ok(PlanResult::new(
+            self.deco.tissue(),
+            &segments,
+            &gas_plan,
+            &oxygen_exposure,
+            OxygenExposure::default(),
+            &cylinders,
+            self.parameters,
+        )
+        .with_gas_shortfalls(&gas_shortfalls)
+        .with_oxygen_used(oxygen_used))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deco::zhl16::builder::ZHL16Builder;
+    use crate::environment::Environment;
+    use crate::gas::GasModel;
+    use crate::limits::Limits;
+    use crate::units::altitude::Altitude;
+    use crate::units::rate::Rate;
+    use crate::units::water_density::SALTWATER;
+    use time::Duration;
+
+    const LOOP_VOLUME: GasConsumption = GasConsumption(6);
+
+    fn parameters() -> Parameters {
+        Parameters::new(
+            Rate::from_metres_per_minute(-10.0),
+            Rate::from_metres_per_minute(20.0),
+            Environment::new(SALTWATER, Altitude(0)),
+            GasConsumptionRate::from_litres_per_minute(20.0),
+            GasConsumptionRate::from_litres_per_minute(15.0),
+            GasModel::Ideal,
+            Limits::default(),
+        )
+    }
+
+    fn plan(cylinders: &[Cylinder]) -> PlanResult {
+        let parameters = parameters();
+        let depth = Depth::from_metres(30.0);
+        let bottom = Segment::new(
+            SegmentType::Bottom,
+            depth,
+            depth,
+            Duration::minutes(40),
+            parameters.ascent_rate(),
+            parameters.descent_rate(),
+        )
+        .unwrap();
+
+        ClosedCircuit::new(
+            ZHL16Builder::new().finish(),
+            &[bottom],
+            Gas::new(21, 0, 79).unwrap(),
+            Setpoints::new(Pressure(0.7), Pressure(1.3), Pressure(1.3)),
+            LOOP_VOLUME,
+            GasConsumptionRate::from_litres_per_minute(1.0),
+            parameters,
+        )
+        .with_cylinders(cylinders)
+        .get_plan()
+        .unwrap()
+    }
+
+    #[test]
+    fn constant_setpoint_bottom_segment_cns() {
+        let plan = plan(&[]);
+        let bottom = plan
+            .segments()
+            .iter()
+            .position(|(segment, _)| segment.segment_type() == SegmentType::Bottom)
+            .unwrap();
+
+        // 40 minutes of the 180 minute NOAA limit at 1.3 bar
+        let cns = plan.segment_oxygen_exposure()[bottom].cns();
+        assert!((cns - 40.0 / 180.0 * 100.0).abs() < 0.01, "{}", cns);
+    }
+
+    #[test]
+    fn diluent_is_used_on_descent_and_ascent() {
+        let plan = plan(&[]);
+        let environment = parameters().environment();
+        let pressure_change =
+            (Depth::from_metres(30.0).pressure(environment) - SURFACE.pressure(environment)).0;
+        let expected = 2.0 * LOOP_VOLUME.0 as f64 * pressure_change;
+
+        let diluent = plan.gas_used()[&Gas::new(21, 0, 79).unwrap()].0 as f64;
+        assert!(diluent > 0.0);
+        assert!(
+            (diluent - expected).abs() <= 2.0,
+            "{} {}",
+            diluent,
+            expected
+        );
+    }
+
+    #[test]
+    fn diluent_and_oxygen_are_drawn_from_cylinders() {
+        let diluent = Cylinder::new(
+            3.0,
+            Pressure(232.0),
+            Pressure(200.0),
+            Pressure(50.0),
+            Gas::new(21, 0, 79).unwrap(),
+        )
+        .unwrap();
+        let oxygen = Cylinder::new(
+            3.0,
+            Pressure(232.0),
+            Pressure(200.0),
+            Pressure(50.0),
+            Gas::new(100, 0, 0).unwrap(),
+        )
+        .unwrap();
+        let plan = plan(&[diluent, oxygen]);
+
+        assert_eq!(
+            plan.cylinders()[0].gas_used(),
+            plan.gas_used()[&Gas::new(21, 0, 79).unwrap()]
+        );
+        assert_eq!(plan.cylinders()[1].gas_used(), plan.oxygen_used());
+        assert!(plan.oxygen_used().0 > 0);
+        assert!(!plan.out_of_gas());
+    }
+}
@@ -0,0 +1,34 @@
+use crate::units::pressure::Pressure;
+
+#[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Setpoints {
+    descent: Pressure,
+    bottom: Pressure,
+    deco: Pressure,
+}
+
+impl Setpoints {
+    pub fn new(descent: Pressure, bottom: Pressure, deco: Pressure) -> Self {
+        Self {
+            descent,
+            bottom,
+            deco,
+        }
+    }
+
+    /// Setpoint used while descending.
+    pub fn descent(&self) -> Pressure {
+        self.descent
+    }
+
+    /// Setpoint used on the bottom segments, including ascents between levels.
+    pub fn bottom(&self) -> Pressure {
+        self.bottom
+    }
+
+    /// Setpoint used during the final ascent and decompression stops.
+    pub fn deco(&self) -> Pressure {
+        self.deco
+    }
+}
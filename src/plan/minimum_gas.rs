@@ -0,0 +1,351 @@
+use crate::gas::Gas;
+use crate::parameter::Parameters;
+use crate::plan::plan_result::PlanResult;
+use crate::plan::PlanError;
+use crate::segment::{Segment, SegmentType};
+use crate::units::consumption::GasConsumption;
+use crate::units::consumption_rate::GasConsumptionRate;
+use crate::units::depth::{Depth, SURFACE};
+use crate::units::pressure::Pressure;
+use crate::util::time_taken;
+use time::Duration;
+
+/// Assumptions used to calculate minimum gas.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinimumGasParameters {
+    divers: u8,
+    stressed_sac: GasConsumptionRate,
+    problem_time: Duration,
+}
+
+impl MinimumGasParameters {
+    /// # Arguments
+    /// * `divers` - Number of divers breathing from the reserve (usually two, for a gas share)
+    /// * `stressed_sac` - SAC rate of each diver while handling the problem and ascending
+    /// * `problem_time` - Time spent solving the problem at depth before the ascent begins
+    pub fn new(divers: u8, stressed_sac: GasConsumptionRate, problem_time: Duration) -> Self {
+        Self {
+            divers,
+            stressed_sac,
+            problem_time,
+        }
+    }
+
+    pub fn divers(&self) -> u8 {
+        self.divers
+    }
+
+    pub fn stressed_sac(&self) -> GasConsumptionRate {
+        self.stressed_sac
+    }
+
+    pub fn problem_time(&self) -> Duration {
+        self.problem_time
+    }
+}
+
+/// Minimum gas (rock bottom) reserve for a single gas in a plan.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinimumGas {
+    gas: Gas,
+    volume: GasConsumption,
+    worst_case_segment: usize,
+    worst_case_depth: Depth,
+    pressure: Option<Pressure>,
+    satisfied: Option<bool>,
+}
+
+impl MinimumGas {
+    /// Calculate the minimum gas required for each gas breathed in a plan.
+    ///
+    /// At the end of every segment, the reserve needed is the gas used by all divers at the stressed
+    /// SAC rate for the problem time, followed by the planned ascent on that gas
+    /// (until the next gas switch, or the surface).
+    /// The worst case is the point in the plan with the largest reserve. Where several points need
+    /// the same reserve, the last of them is reported, as the most gas has been used by then.
+    /// # Errors
+    /// Returns an error if the problem solving or ascent segments cannot be built from the plan.
+    pub fn calculate(
+        plan: &PlanResult,
+        minimum_gas_parameters: MinimumGasParameters,
+        parameters: Parameters,
+    ) -> Result<Vec<Self>, PlanError> {
+        let segments = plan.segments();
+        let environment = parameters.environment();
+        let sac = GasConsumptionRate::from_millilitres_per_minute(
+            minimum_gas_parameters.stressed_sac.millilitres_per_minute()
+                * minimum_gas_parameters.divers as u32,
+        );
+
+        let mut minimum_gases: Vec<MinimumGas> = Vec::new();
+        // Gas used so far from each gas, to check the reserve is still available at each point
+        let mut used: Vec<(Gas, GasConsumption)> = Vec::new();
+
+        for (index, (segment, gas)) in segments.iter().enumerate() {
+            if segment.segment_type() == SegmentType::NoDeco {
+                continue;
+            }
+
+            let sac_rate = match segment.segment_type() {
+                SegmentType::DecoStop => parameters.sac_deco(),
+                _ => parameters.sac_bottom(),
+            };
+            let position = used
+                .iter()
+                .position(|(used_gas, _)| used_gas == gas)
+                .unwrap_or_else(|| {
+                    used.push((*gas, GasConsumption::default()));
+                    used.len() - 1
+                });
+            used[position].1 += segment.gas_consumed(sac_rate, environment);
+            let used_so_far = used[position].1;
+
+            // Problem solving at the end of the segment
+            let depth = segment.end_depth();
+            let problem = Segment::new(
+                SegmentType::Bottom,
+                depth,
+                depth,
+                minimum_gas_parameters.problem_time,
+                parameters.ascent_rate(),
+                parameters.descent_rate(),
+            )?;
+            let mut volume = problem.gas_consumed(sac, environment);
+
+            // Then follow the planned ascent and stops on the same gas until the next switch.
+            // Any remaining bottom time is abandoned.
+            let mut final_depth = depth;
+            for (next, _) in segments[index + 1..]
+                .iter()
+                .take_while(|(next, next_gas)| {
+                    next_gas == gas && next.segment_type() != SegmentType::NoDeco
+                })
+                .filter(|(next, _)| match next.segment_type() {
+                    SegmentType::DecoStop => true,
+                    SegmentType::AscDesc => next.end_depth() < next.start_depth(),
+                    _ => false,
+                })
+            {
+                volume += next.gas_consumed(sac, environment);
+                final_depth = next.end_depth();
+            }
+
+            // A plan which finishes on this gas does not include the final ascent
+            let switches = segments[index + 1..].iter().any(|(next, next_gas)| {
+                next_gas != gas && next.segment_type() != SegmentType::NoDeco
+            });
+            if !switches && final_depth > SURFACE {
+                let ascent = Segment::new(
+                    SegmentType::AscDesc,
+                    final_depth,
+                    SURFACE,
+                    time_taken(parameters.ascent_rate(), final_depth, SURFACE),
+                    parameters.ascent_rate(),
+                    parameters.descent_rate(),
+                )?;
+                volume += ascent.gas_consumed(sac, environment);
+            }
+
+            let cylinders = plan
+                .cylinders()
+                .iter()
+                .filter(|usage| usage.cylinder().gas() == *gas)
+                .collect::<Vec<_>>();
+            let water_volume = cylinders
+                .iter()
+                .map(|usage| usage.cylinder().water_volume())
+                .sum::<f64>();
+            let start_volume = cylinders
+                .iter()
+                .map(|usage| usage.cylinder().start_volume(parameters.gas_model()))
+                .fold(GasConsumption::default(), |acc, volume| acc + volume);
+            let satisfied = if cylinders.is_empty() {
+                None
+            } else {
+                Some(start_volume.0.saturating_sub(used_so_far.0) >= volume.0)
+            };
+
+            let candidate = MinimumGas {
+                gas: *gas,
+                volume,
+                worst_case_segment: index,
+                worst_case_depth: depth,
+                pressure: if cylinders.is_empty() {
+                    None
+                } else {
+                    Some(
+                        parameters
+                            .gas_model()
+                            .pressure(gas, water_volume, volume.0 as f64),
+                    )
+                },
+                satisfied,
+            };
+
+            match minimum_gases
+                .iter_mut()
+                .find(|existing| existing.gas == *gas)
+            {
+                Some(existing) => {
+                    let satisfied = match (existing.satisfied, candidate.satisfied) {
+                        (Some(a), Some(b)) => Some(a && b),
+                        _ => None,
+                    };
+                    if candidate.volume >= existing.volume {
+                        *existing = candidate;
+                    }
+                    existing.satisfied = satisfied;
+                }
+                None => minimum_gases.push(candidate),
+            }
+        }
+
+        Ok(minimum_gases)
+    }
+
+    pub fn gas(&self) -> Gas {
+        self.gas
+    }
+
+    /// Reserve volume required at the worst-case point (measured in litres at surface pressure).
+    pub fn volume(&self) -> GasConsumption {
+        self.volume
+    }
+
+    /// Index of the plan segment at the end of which the worst case occurs.
+    pub fn worst_case_segment(&self) -> usize {
+        self.worst_case_segment
+    }
+
+    pub fn worst_case_depth(&self) -> Depth {
+        self.worst_case_depth
+    }
+
+    /// Reserve expressed as a pressure across all cylinders holding the gas.
+    /// `None` if no cylinders holding the gas were given to the plan.
+    pub fn pressure(&self) -> Option<Pressure> {
+        self.pressure
+    }
+
+    /// Whether the cylinders holding the gas still contain the reserve at every point of the plan.
+    /// `None` if no cylinders holding the gas were given to the plan.
+    pub fn satisfied(&self) -> Option<bool> {
+        self.satisfied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cylinder::{Cylinder, CylinderUsage};
+    use crate::environment::Environment;
+    use crate::gas::GasModel;
+    use crate::limits::Limits;
+    use crate::oxygen::OxygenExposure;
+    use crate::tissue::Tissue;
+    use crate::units::altitude::Altitude;
+    use crate::units::rate::Rate;
+    use crate::units::water_density::SALTWATER;
+    use std::collections::HashMap;
+
+    fn parameters() -> Parameters {
+        Parameters::new(
+            Rate::from_metres_per_minute(-10.0),
+            Rate::from_metres_per_minute(20.0),
+            Environment::new(SALTWATER, Altitude(0)),
+            GasConsumptionRate::from_litres_per_minute(20.0),
+            GasConsumptionRate::from_litres_per_minute(15.0),
+            GasModel::Ideal,
+            Limits::default(),
+        )
+    }
+
+    fn minimum_gas(water_volume: f64) -> MinimumGas {
+        let parameters = parameters();
+        let air = Gas::new(21, 0, 79).unwrap();
+        let depth = Depth::from_metres(30.0);
+        let segment = |segment_type, start, end, time| {
+            Segment::new(
+                segment_type,
+                start,
+                end,
+                time,
+                parameters.ascent_rate(),
+                parameters.descent_rate(),
+            )
+            .unwrap()
+        };
+        let segments = [
+            (
+                segment(SegmentType::AscDesc, SURFACE, depth, Duration::seconds(90)),
+                air,
+            ),
+            (
+                segment(SegmentType::Bottom, depth, depth, Duration::minutes(20)),
+                air,
+            ),
+            (
+                segment(SegmentType::AscDesc, depth, SURFACE, Duration::minutes(3)),
+                air,
+            ),
+        ];
+        let cylinder = Cylinder::new(
+            water_volume,
+            Pressure(232.0),
+            Pressure(200.0),
+            Pressure(50.0),
+            air,
+        )
+        .unwrap();
+        let (cylinders, _) = CylinderUsage::drain(&[cylinder], &[], parameters.gas_model());
+        let plan = PlanResult::new(
+            Tissue::default(),
+            &segments,
+            &HashMap::new(),
+            &[OxygenExposure::default(); 3],
+            OxygenExposure::default(),
+            &cylinders,
+            parameters,
+        );
+
+        let minimum_gas = MinimumGas::calculate(
+            &plan,
+            MinimumGasParameters::new(
+                2,
+                GasConsumptionRate::from_litres_per_minute(20.0),
+                Duration::minute(),
+            ),
+            parameters,
+        )
+        .unwrap();
+        assert_eq!(minimum_gas.len(), 1);
+        minimum_gas[0]
+    }
+
+    #[test]
+    fn worst_case_is_the_end_of_the_bottom() {
+        let minimum_gas = minimum_gas(12.0);
+
+        assert_eq!(minimum_gas.worst_case_segment(), 1);
+        assert_eq!(minimum_gas.worst_case_depth(), Depth::from_metres(30.0));
+    }
+
+    #[test]
+    fn reserve_covers_problem_and_ascent() {
+        // Two divers at 20 L/min: one minute at 4.08 bar, then three minutes at 2.55 bar
+        let minimum_gas = minimum_gas(12.0);
+
+        assert_eq!(minimum_gas.volume(), GasConsumption(468));
+        assert_eq!(minimum_gas.pressure(), Some(Pressure(39.0)));
+    }
+
+    #[test]
+    fn cylinder_must_hold_the_reserve_at_the_worst_case() {
+        // 1709 L is used by the end of the bottom, leaving 691 L in a 12 L cylinder but only
+        // 291 L in a 10 L cylinder
+        assert_eq!(minimum_gas(12.0).satisfied(), Some(true));
+        assert_eq!(minimum_gas(10.0).satisfied(), Some(false));
+    }
+}
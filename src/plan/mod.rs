@@ -1,8 +1,33 @@
+use crate::deco::DecoError;
 use crate::plan::plan_result::PlanResult;
+use crate::segment::DiveSegmentError;
+use thiserror::Error;
 
+pub mod closed_circuit;
+pub mod minimum_gas;
 pub mod open_circuit;
 pub mod plan_result;
+pub mod rendered_plan;
+pub mod repetitive;
+pub mod runtable;
+pub mod svg;
+pub mod warning;
+
+#[derive(Copy, Clone, Debug, Error, Eq, PartialEq, Hash)]
+pub enum PlanError {
+    #[error("plan has no bottom segments")]
+    NoSegments,
+    #[error("gas of bottom segment {0} exceeds the maximum gas density")]
+    BottomGasTooDense(usize),
+    #[error("invalid segment: {0}")]
+    Segment(#[from] DiveSegmentError),
+    #[error("decompression model error: {0}")]
+    Deco(#[from] DecoError),
+}
 
 pub trait DivePlan {
-    fn get_plan(self) -> PlanResult;
+    /// Calculate the plan.
+    /// # Errors
+    /// Returns an error if the plan cannot be calculated from the given segments.
+    fn get_plan(self) -> Result<PlanResult, PlanError>;
 }
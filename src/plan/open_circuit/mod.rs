@@ -1,9 +1,11 @@
+use crate::cylinder::{Cylinder, CylinderUsage};
 use crate::deco::DecoAlgorithm;
 use crate::environment::Environment;
 use crate::gas::Gas;
+use crate::oxygen::OxygenExposure;
 use crate::parameter::Parameters;
-use crate::plan::plan_result::PlanResult;
-use crate::plan::DivePlan;
+use crate::plan::plan_result::{DensityViolation, PlanResult};
+use crate::plan::{DivePlan, PlanError};
 use crate::segment::SegmentType::{AscDesc, DecoStop};
 use crate::segment::{Segment, SegmentType};
 use crate::units::consumption::GasConsumption;
@@ -25,6 +27,7 @@ where
     deco: T,
     bottom_segments: Vec<(Segment, Gas)>,
     deco_gases: HashSet<(Gas, Option<Depth>)>,
+    cylinders: Vec<Cylinder>,
     parameters: Parameters,
 }
 
@@ -32,18 +35,43 @@ impl<T> OpenCircuit<T>
 where
     T: DecoAlgorithm + Clone,
 {
+    /// Create a new open circuit plan.
+    /// # Arguments
+    /// * `deco` - Decompression algorithm to use
+    /// * `bottom_segments` - Segments of the dive, with the gas breathed on each
+    /// * `deco_gases` - Gases available for decompression, with an optional switch depth
+    /// * `cylinders` - Cylinders carried, used to report remaining gas. May be empty.
+    /// * `parameters` - Dive parameters
+    /// # Errors
+    /// Returns an error if no bottom segments are given, or if a bottom segment is breathed on a gas
+    /// denser than the maximum density.
     pub fn new(
         deco: T,
         bottom_segments: &[(Segment, Gas)],
         deco_gases: &[(Gas, Option<Depth>)],
+        cylinders: &[Cylinder],
         parameters: Parameters,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, PlanError> {
+        if bottom_segments.is_empty() {
+            return Err(PlanError::NoSegments);
+        }
+
+        let too_dense = DensityViolation::find(
+            bottom_segments,
+            parameters.limits().maximum_density(),
+            parameters.environment(),
+        );
+        if let Some(violation) = too_dense.first() {
+            return Err(PlanError::BottomGasTooDense(violation.segment()));
+        }
+
+        Ok(Self {
             deco,
             bottom_segments: bottom_segments.to_vec(),
-            deco_gases: deco_gases.to_vec().into_iter().collect::<HashSet<_>>(),
+            deco_gases: deco_gases.iter().copied().collect::<HashSet<_>>(),
+            cylinders: cylinders.to_vec(),
             parameters,
-        }
+        })
     }
 
     fn find_gas_switch_point(
@@ -51,6 +79,8 @@ where
         current_gas: &Gas,
         available_gases: &HashSet<(Gas, Option<Depth>)>,
         max_pp_o2: Pressure,
+        max_density: f64,
+        icd_limit: Option<f64>,
         environment: Environment,
     ) -> Option<(Segment, Gas)> {
         for stop in segments
@@ -74,6 +104,13 @@ where
                 .filter(|(gas, _)| {
                     gas.equivalent_narcotic_depth(stop.start_depth()) <= stop.start_depth()
                 })
+                // Remove gases which are too dense
+                .filter(|(gas, _)| gas.density(stop.start_depth(), environment) <= max_density)
+                // Remove switches which risk isobaric counterdiffusion
+                .filter(|(gas, _)| match icd_limit {
+                    Some(ratio) => !current_gas.violates_icd(gas, ratio),
+                    None => true,
+                })
                 // Sort by ppO2 (descending)
                 .sorted_by(|(fst, _), (snd, _)| {
                     snd.pp_o2(stop.start_depth(), environment)
@@ -98,7 +135,7 @@ where
         (start_segment, start_gas): &(Segment, Gas),
         end: Option<&(Segment, Gas)>,
         stops_performed: &mut Vec<(Segment, Gas)>,
-    ) -> T {
+    ) -> Result<T, PlanError> {
         // If end segment is defined, check if there is a depth change
         if let Some((end_segment, _)) = end {
             match start_segment.end_depth().cmp(&end_segment.start_depth()) {
@@ -115,20 +152,19 @@ where
                         ),
                         self.parameters.ascent_rate(),
                         self.parameters.descent_rate(),
-                    )
-                    .unwrap();
+                    )?;
                     // Add to model
                     running_model = running_model.add_segment(
                         &descent,
-                        &start_gas,
+                        start_gas,
                         self.parameters.environment(),
-                    );
+                    )?;
                     stops_performed.push((descent, *start_gas));
-                    return running_model;
+                    return Ok(running_model);
                 }
                 Ordering::Equal => {
                     // If both are equal then no segments to add.
-                    return running_model;
+                    return Ok(running_model);
                 }
                 Ordering::Greater => {} // Continue to main algorithm
             }
@@ -144,9 +180,9 @@ where
             .get_stops(
                 self.parameters.ascent_rate(),
                 self.parameters.descent_rate(),
-                &start_gas,
+                start_gas,
                 self.parameters.environment(),
-            )
+            )?
             .into_iter()
             .take_while(|x| x.start_depth() > end_depth)
             .collect::<Vec<_>>();
@@ -159,9 +195,11 @@ where
         // Determine a switch-point
         let switch_point = OpenCircuit::<T>::find_gas_switch_point(
             &stops,
-            &start_gas,
+            start_gas,
             &available_gases,
             end.map(|_| PPO2_MAXIMUM_DIVE).unwrap_or(PPO2_MAXIMUM_DECO),
+            self.parameters.limits().maximum_density(),
+            self.parameters.limits().icd_limit(),
             self.parameters.environment(),
         );
 
@@ -175,7 +213,7 @@ where
                 .take_while(|stop| stop.start_depth() > switch_point.start_depth())
             {
                 running_model =
-                    running_model.add_segment(&stop, &start_gas, self.parameters.environment());
+                    running_model.add_segment(stop, start_gas, self.parameters.environment())?;
                 stops_performed.push((*stop, *start_gas));
             }
 
@@ -187,32 +225,32 @@ where
                     self.parameters.descent_rate(),
                     &switch_gas,
                     self.parameters.environment(),
-                )
+                )?
                 .into_iter()
                 .find(|stop| {
                     stop.segment_type() == DecoStop
                         && stop.start_depth() == switch_point.start_depth()
-                })
-                .unwrap_or_else(|| {
-                    Segment::new(
-                        SegmentType::DecoStop,
-                        switch_point.start_depth(),
-                        switch_point.end_depth(),
-                        Duration::minute(),
-                        self.parameters.ascent_rate(),
-                        self.parameters.descent_rate(),
-                    )
-                    .unwrap()
                 });
+            let new_stop = match new_stop {
+                Some(stop) => stop,
+                None => Segment::new(
+                    SegmentType::DecoStop,
+                    switch_point.start_depth(),
+                    switch_point.end_depth(),
+                    Duration::minute(),
+                    self.parameters.ascent_rate(),
+                    self.parameters.descent_rate(),
+                )?,
+            };
 
             running_model =
-                running_model.add_segment(&new_stop, &switch_gas, self.parameters.environment());
+                running_model.add_segment(&new_stop, &switch_gas, self.parameters.environment())?;
             stops_performed.push((new_stop, switch_gas));
             self.level_to_level(running_model, &(new_stop, switch_gas), end, stops_performed)
         } else {
             // Push segments and return
             stops_performed.append(&mut stops.into_iter().zip(iter::repeat(*start_gas)).collect());
-            running_model
+            Ok(running_model)
         }
     }
 }
@@ -221,14 +259,15 @@ impl<T> DivePlan for OpenCircuit<T>
 where
     T: DecoAlgorithm + Clone,
 {
-    fn get_plan(mut self) -> PlanResult {
+    fn get_plan(mut self) -> Result<PlanResult, PlanError> {
         let mut segments: Vec<(Segment, Gas)> = Vec::new();
+        let (first, first_gas) = *self.bottom_segments.first().ok_or(PlanError::NoSegments)?;
 
         // Adjust diver depth to beginning of segments
-        let asc_desc_to_beginning = {
-            let start_depth = self.deco.model_depth();
-            let end_depth = self.bottom_segments[0].0.start_depth();
-            Segment::new(
+        let start_depth = self.deco.model_depth();
+        let end_depth = first.start_depth();
+        if start_depth != end_depth {
+            let asc_desc_to_beginning = Segment::new(
                 SegmentType::AscDesc,
                 start_depth,
                 end_depth,
@@ -243,16 +282,15 @@ where
                 ),
                 self.parameters.ascent_rate(),
                 self.parameters.descent_rate(),
-            )
-        }
-        .unwrap();
+            )?;
 
-        self.deco = self.deco.add_segment(
-            &asc_desc_to_beginning,
-            &self.bottom_segments[0].1,
-            self.parameters.environment(),
-        );
-        segments.push((asc_desc_to_beginning, self.bottom_segments[0].1));
+            self.deco = self.deco.add_segment(
+                &asc_desc_to_beginning,
+                &first_gas,
+                self.parameters.environment(),
+            )?;
+            segments.push((asc_desc_to_beginning, first_gas));
+        }
 
         // Use a sliding window to go from segment to segment.
         for window in self.bottom_segments.windows(2) {
@@ -261,37 +299,156 @@ where
 
             self.deco = self
                 .deco
-                .add_segment(&start.0, &start.1, self.parameters.environment());
+                .add_segment(&start.0, &start.1, self.parameters.environment())?;
             segments.push(start);
 
             self.deco =
-                self.level_to_level(self.deco.clone(), &start, Some(&end), &mut stops_performed);
+                self.level_to_level(self.deco.clone(), &start, Some(&end), &mut stops_performed)?;
             segments.append(&mut stops_performed);
         }
 
         // Final element not captured.
-        let final_stop = self.bottom_segments.last().unwrap();
+        let final_stop = *self.bottom_segments.last().ok_or(PlanError::NoSegments)?;
         self.deco =
             self.deco
-                .add_segment(&final_stop.0, &final_stop.1, self.parameters.environment());
-        segments.push(*final_stop);
+                .add_segment(&final_stop.0, &final_stop.1, self.parameters.environment())?;
+        segments.push(final_stop);
 
         let mut stops_performed: Vec<(Segment, Gas)> = Vec::new();
-        self.deco = self.level_to_level(self.deco.clone(), &final_stop, None, &mut stops_performed);
+        self.deco =
+            self.level_to_level(self.deco.clone(), &final_stop, None, &mut stops_performed)?;
         segments.append(&mut stops_performed);
 
+        let segment_gas_used = segments
+            .iter()
+            .enumerate()
+            .map(|(index, (segment, gas))| {
+                let consumed = match segment.segment_type() {
+                    SegmentType::NoDeco => GasConsumption(0),
+                    SegmentType::DecoStop => segment
+                        .gas_consumed(self.parameters.sac_deco(), self.parameters.environment()),
+                    _ => segment
+                        .gas_consumed(self.parameters.sac_bottom(), self.parameters.environment()),
+                };
+                (index, *gas, consumed)
+            })
+            .collect::<Vec<_>>();
+
         let mut gas_plan = HashMap::new();
-        for (segment, gas) in &segments {
-            *gas_plan.entry(*gas).or_default() += match segment.segment_type() {
-                SegmentType::NoDeco => GasConsumption(0),
-                SegmentType::DecoStop => {
-                    segment.gas_consumed(self.parameters.sac_deco(), self.parameters.environment())
-                }
-                _ => segment
-                    .gas_consumed(self.parameters.sac_bottom(), self.parameters.environment()),
-            };
+        for (_, gas, consumed) in &segment_gas_used {
+            *gas_plan.entry(*gas).or_default() += *consumed;
         }
+        let (cylinders, gas_shortfalls) = CylinderUsage::drain(
+            &self.cylinders,
+            &segment_gas_used,
+            self.parameters.gas_model(),
+        );
+
+        let oxygen_exposure = segments
+            .iter()
+            .map(|(segment, gas)| {
+                OxygenExposure::from_segment(segment, gas, self.parameters.environment())
+            })
+            .collect::<Vec<_>>();
+
+        Ok(PlanResult::new(
+            self.deco.tissue(),
+            &segments,
+            &gas_plan,
+            &oxygen_exposure,
+            OxygenExposure::default(),
+            &cylinders,
+            self.parameters,
+        )
+        .with_gas_shortfalls(&gas_shortfalls))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deco::zhl16::builder::ZHL16Builder;
+    use crate::gas::GasModel;
+    use crate::limits::Limits;
+    use crate::plan::repetitive::{RepetitiveDives, RepetitiveStep};
+    use crate::units::altitude::Altitude;
+    use crate::units::consumption_rate::GasConsumptionRate;
+    use crate::units::rate::Rate;
+    use crate::units::water_density::SALTWATER;
+
+    fn parameters() -> Parameters {
+        Parameters::new(
+            Rate::from_metres_per_minute(-10.0),
+            Rate::from_metres_per_minute(20.0),
+            Environment::new(SALTWATER, Altitude(0)),
+            GasConsumptionRate::from_litres_per_minute(20.0),
+            GasConsumptionRate::from_litres_per_minute(15.0),
+            GasModel::Ideal,
+            Limits::default(),
+        )
+    }
+
+    fn bottom(metres: f64) -> Segment {
+        let parameters = parameters();
+        let depth = Depth::from_metres(metres);
+        Segment::new(
+            SegmentType::Bottom,
+            depth,
+            depth,
+            Duration::minutes(20),
+            parameters.ascent_rate(),
+            parameters.descent_rate(),
+        )
+        .unwrap()
+    }
+
+    fn plan(bottom_segments: &[(Segment, Gas)]) -> Result<PlanResult, PlanError> {
+        OpenCircuit::new(
+            ZHL16Builder::new().finish(),
+            bottom_segments,
+            &[],
+            &[],
+            parameters(),
+        )?
+        .get_plan()
+    }
+
+    #[test]
+    fn plan_without_segments_is_an_error() {
+        assert_eq!(plan(&[]).unwrap_err(), PlanError::NoSegments);
+    }
+
+    #[test]
+    fn bottom_gas_too_dense_is_an_error() {
+        // Air is 7.1 g/L at 45 m, above the 6.2 g/L maximum
+        let air = Gas::new(21, 0, 79).unwrap();
+        let segments = [(bottom(20.0), air), (bottom(45.0), air)];
+
+        assert_eq!(
+            plan(&segments).unwrap_err(),
+            PlanError::BottomGasTooDense(1)
+        );
+    }
+
+    #[test]
+    fn repetitive_dive_errors_are_returned() {
+        let dives = RepetitiveDives::new(
+            ZHL16Builder::new().finish(),
+            &[RepetitiveStep::Dive {
+                bottom_segments: Vec::new(),
+                deco_gases: Vec::new(),
+                cylinders: Vec::new(),
+            }],
+            parameters(),
+        );
+
+        assert_eq!(dives.get_plans().unwrap_err(), PlanError::NoSegments);
+    }
+
+    #[test]
+    fn valid_plan_is_calculated() {
+        let air = Gas::new(21, 0, 79).unwrap();
 
-        PlanResult::new(self.deco.tissue(), &segments, &gas_plan)
+        assert!(plan(&[(bottom(30.0), air)]).is_ok());
     }
 }
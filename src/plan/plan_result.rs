@@ -1,15 +1,116 @@
+use crate::cylinder::{CylinderUsage, GasShortfall};
+use crate::deco::{DecoAlgorithm, DecoError};
+use crate::environment::Environment;
 use crate::gas::Gas;
-use crate::segment::Segment;
+use crate::oxygen::OxygenExposure;
+use crate::parameter::Parameters;
+use crate::plan::rendered_plan::RenderedPlan;
+use crate::plan::runtable::{Runtable, RuntableOptions};
+use crate::plan::svg::{self, SvgOptions};
+use crate::plan::warning::PlanWarning;
+use crate::segment::{Segment, SegmentType};
 use crate::tissue::Tissue;
 use crate::units::consumption::GasConsumption;
+use crate::units::unit_system::UnitSystem;
 use std::collections::HashMap;
 
+/// A segment breathed on a gas denser than the recommended limit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DensityViolation {
+    segment: usize,
+    gas: Gas,
+    density: f64,
+}
+
+impl DensityViolation {
+    /// Find every segment where the gas density at the deepest point exceeds `limit` (in g/L).
+    pub fn find(segments: &[(Segment, Gas)], limit: f64, environment: Environment) -> Vec<Self> {
+        segments
+            .iter()
+            .enumerate()
+            .filter(|(_, (segment, _))| segment.segment_type() != SegmentType::NoDeco)
+            .map(|(index, (segment, gas))| DensityViolation {
+                segment: index,
+                gas: *gas,
+                density: gas.density(segment.start_depth().max(segment.end_depth()), environment),
+            })
+            .filter(|violation| violation.density > limit)
+            .collect()
+    }
+
+    /// Index of the offending segment in [`PlanResult::segments`].
+    pub fn segment(&self) -> usize {
+        self.segment
+    }
+    pub fn gas(&self) -> Gas {
+        self.gas
+    }
+    /// Density of the gas at the deepest point of the segment (measured in g/L).
+    pub fn density(&self) -> f64 {
+        self.density
+    }
+}
+
+/// A gas switch which breaks the isobaric counterdiffusion rule of fifths.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IcdViolation {
+    segment: usize,
+    from: Gas,
+    to: Gas,
+}
+
+impl IcdViolation {
+    /// Find every gas switch where the nitrogen increase exceeds `ratio` times the helium decrease.
+    pub fn find(segments: &[(Segment, Gas)], ratio: f64) -> Vec<Self> {
+        segments
+            .iter()
+            .enumerate()
+            .filter(|(_, (segment, _))| segment.segment_type() != SegmentType::NoDeco)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .filter_map(|window| {
+                let ((_, (_, from)), (index, (_, to))) = (window[0], window[1]);
+                if from.violates_icd(to, ratio) {
+                    Some(IcdViolation {
+                        segment: index,
+                        from: *from,
+                        to: *to,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Index of the first segment breathed on the new gas in [`PlanResult::segments`].
+    pub fn segment(&self) -> usize {
+        self.segment
+    }
+    pub fn from(&self) -> Gas {
+        self.from
+    }
+    pub fn to(&self) -> Gas {
+        self.to
+    }
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlanResult {
     end_tissue: Tissue,
     segments: Vec<(Segment, Gas)>,
     gas_used: HashMap<Gas, GasConsumption>,
+    oxygen_used: GasConsumption,
+    oxygen_exposure: Vec<OxygenExposure>,
+    residual_oxygen_exposure: OxygenExposure,
+    cylinders: Vec<CylinderUsage>,
+    gas_shortfalls: Vec<GasShortfall>,
+    density_violations: Vec<DensityViolation>,
+    icd_violations: Vec<IcdViolation>,
+    warnings: Vec<PlanWarning>,
 }
 
 impl PlanResult {
@@ -22,6 +123,72 @@ impl PlanResult {
     pub fn gas_used(&self) -> &HashMap<Gas, GasConsumption> {
         &self.gas_used
     }
+    /// Oxygen added to a closed circuit loop, kept apart from [`PlanResult::gas_used`] as it
+    /// comes from its own cylinder. Zero for open circuit plans.
+    pub fn oxygen_used(&self) -> GasConsumption {
+        self.oxygen_used
+    }
+    /// Oxygen exposure of each segment, in the same order as [`PlanResult::segments`].
+    pub fn segment_oxygen_exposure(&self) -> &Vec<OxygenExposure> {
+        &self.oxygen_exposure
+    }
+    /// Oxygen exposure carried over from previous dives.
+    pub fn residual_oxygen_exposure(&self) -> OxygenExposure {
+        self.residual_oxygen_exposure
+    }
+    /// Cumulative oxygen exposure at the end of the dive, including any residual exposure.
+    pub fn oxygen_exposure(&self) -> OxygenExposure {
+        self.oxygen_exposure
+            .iter()
+            .fold(self.residual_oxygen_exposure, |acc, &exposure| {
+                acc + exposure
+            })
+    }
+    /// Gas drawn from each cylinder, in the order the cylinders were given to the planner.
+    pub fn cylinders(&self) -> &Vec<CylinderUsage> {
+        &self.cylinders
+    }
+    /// Gas needed by each segment which the cylinders could not supply.
+    pub fn gas_shortfalls(&self) -> &Vec<GasShortfall> {
+        &self.gas_shortfalls
+    }
+    /// Whether the plan needs more of any gas than the cylinders hold. Emptying one cylinder is
+    /// not enough, as the diver may carry others with the same gas.
+    pub fn out_of_gas(&self) -> bool {
+        !self.gas_shortfalls.is_empty()
+    }
+    /// Segments breathed on a gas denser than the recommended limit.
+    pub fn density_violations(&self) -> &Vec<DensityViolation> {
+        &self.density_violations
+    }
+    /// Gas switches which break the configured isobaric counterdiffusion limit. Always empty if
+    /// no limit is configured.
+    pub fn icd_violations(&self) -> &Vec<IcdViolation> {
+        &self.icd_violations
+    }
+    /// Problems found in the plan, such as hypoxic gases or fast ascents.
+    pub fn warnings(&self) -> &Vec<PlanWarning> {
+        &self.warnings
+    }
+    /// Convert the plan to the given unit system for presentation.
+    pub fn render(&self, units: UnitSystem) -> RenderedPlan {
+        RenderedPlan::new(self, units)
+    }
+    /// Tabulate the plan as a runtable, with travel folded into the following level.
+    pub fn runtable(&self, parameters: Parameters, options: RuntableOptions) -> Runtable {
+        Runtable::new(self, parameters, options)
+    }
+    /// Draw the plan as an SVG profile chart. See [`svg::render`].
+    /// # Errors
+    /// Returns an error if the plan cannot be replayed through the decompression model.
+    pub fn to_svg<T: DecoAlgorithm>(
+        &self,
+        deco: T,
+        environment: Environment,
+        options: SvgOptions,
+    ) -> Result<String, DecoError> {
+        svg::render(self, deco, environment, options)
+    }
 }
 
 impl PlanResult {
@@ -29,11 +196,131 @@ impl PlanResult {
         end_tissue: Tissue,
         segments: &[(Segment, Gas)],
         gas_used: &HashMap<Gas, GasConsumption>,
+        oxygen_exposure: &[OxygenExposure],
+        residual_oxygen_exposure: OxygenExposure,
+        cylinders: &[CylinderUsage],
+        parameters: Parameters,
     ) -> Self {
+        let limits = parameters.limits();
         Self {
             end_tissue,
             segments: segments.to_vec(),
             gas_used: gas_used.clone(),
+            oxygen_used: GasConsumption::default(),
+            oxygen_exposure: oxygen_exposure.to_vec(),
+            residual_oxygen_exposure,
+            cylinders: cylinders.to_vec(),
+            gas_shortfalls: Vec::new(),
+            density_violations: DensityViolation::find(
+                segments,
+                limits.recommended_density(),
+                parameters.environment(),
+            ),
+            icd_violations: match limits.icd_limit() {
+                Some(ratio) => IcdViolation::find(segments, ratio),
+                None => Vec::new(),
+            },
+            warnings: PlanWarning::find(segments, parameters),
         }
     }
+
+    /// Record the gas the cylinders could not supply.
+    pub(crate) fn with_gas_shortfalls(mut self, gas_shortfalls: &[GasShortfall]) -> Self {
+        self.gas_shortfalls = gas_shortfalls.to_vec();
+        self
+    }
+
+    /// Record the oxygen added to a closed circuit loop.
+    pub(crate) fn with_oxygen_used(mut self, oxygen_used: GasConsumption) -> Self {
+        self.oxygen_used = oxygen_used;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Environment;
+    use crate::gas::GasModel;
+    use crate::limits::{
+        Limits, DENSITY_MAXIMUM, DENSITY_RECOMMENDED, END_MAXIMUM, ICD_RULE_OF_FIFTHS,
+    };
+    use crate::units::altitude::Altitude;
+    use crate::units::consumption_rate::GasConsumptionRate;
+    use crate::units::depth::Depth;
+    use crate::units::rate::Rate;
+    use crate::units::water_density::SALTWATER;
+    use time::Duration;
+
+    fn parameters(icd_limit: Option<f64>) -> Parameters {
+        Parameters::new(
+            Rate::from_metres_per_minute(-10.0),
+            Rate::from_metres_per_minute(20.0),
+            Environment::new(SALTWATER, Altitude(0)),
+            GasConsumptionRate::from_litres_per_minute(20.0),
+            GasConsumptionRate::from_litres_per_minute(15.0),
+            GasModel::Ideal,
+            Limits::new(DENSITY_RECOMMENDED, DENSITY_MAXIMUM, icd_limit, END_MAXIMUM),
+        )
+    }
+
+    /// A stop on the bottom gas followed by a stop on the deco gas.
+    fn icd_violations(deco_gas: Gas, icd_limit: Option<f64>) -> Vec<IcdViolation> {
+        let parameters = parameters(icd_limit);
+        let stop = |metres| {
+            let depth = Depth::from_metres(metres);
+            Segment::new(
+                SegmentType::DecoStop,
+                depth,
+                depth,
+                Duration::minutes(2),
+                parameters.ascent_rate(),
+                parameters.descent_rate(),
+            )
+            .unwrap()
+        };
+        let segments = [
+            (stop(24.0), Gas::new(18, 45, 37).unwrap()),
+            (stop(21.0), deco_gas),
+        ];
+
+        PlanResult::new(
+            Tissue::default(),
+            &segments,
+            &HashMap::new(),
+            &[OxygenExposure::default(); 2],
+            OxygenExposure::default(),
+            &[],
+            parameters,
+        )
+        .icd_violations()
+        .clone()
+    }
+
+    #[test]
+    fn switch_breaking_the_rule_of_fifths_is_reported() {
+        // N2 rises by 13% while He falls by 45%
+        let ean50 = Gas::new(50, 0, 50).unwrap();
+        let violations = icd_violations(ean50, Some(ICD_RULE_OF_FIFTHS));
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].segment(), 1);
+        assert_eq!(violations[0].from(), Gas::new(18, 45, 37).unwrap());
+        assert_eq!(violations[0].to(), ean50);
+    }
+
+    #[test]
+    fn switch_within_the_rule_of_fifths_is_not_reported() {
+        // N2 rises by 3% while He falls by 20%
+        let trimix = Gas::new(35, 25, 40).unwrap();
+
+        assert!(icd_violations(trimix, Some(ICD_RULE_OF_FIFTHS)).is_empty());
+    }
+
+    #[test]
+    fn switches_are_not_checked_without_a_limit() {
+        let ean50 = Gas::new(50, 0, 50).unwrap();
+
+        assert!(icd_violations(ean50, None).is_empty());
+    }
 }
@@ -0,0 +1,89 @@
+use crate::gas::Gas;
+use crate::plan::plan_result::PlanResult;
+use crate::segment::SegmentType;
+use crate::units::unit_system::UnitSystem;
+use std::collections::HashMap;
+use time::Duration;
+
+/// A plan segment with depths converted to a given unit system.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RenderedSegment {
+    segment_type: SegmentType,
+    start_depth: f64,
+    end_depth: f64,
+    time: Duration,
+    gas: Gas,
+}
+
+impl RenderedSegment {
+    pub fn segment_type(&self) -> SegmentType {
+        self.segment_type
+    }
+    pub fn start_depth(&self) -> f64 {
+        self.start_depth
+    }
+    pub fn end_depth(&self) -> f64 {
+        self.end_depth
+    }
+    pub fn time(&self) -> &Duration {
+        &self.time
+    }
+    pub fn gas(&self) -> Gas {
+        self.gas
+    }
+}
+
+/// A [`PlanResult`] with all quantities converted to a given unit system.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RenderedPlan {
+    units: UnitSystem,
+    segments: Vec<RenderedSegment>,
+    gas_used: HashMap<Gas, f64>,
+    oxygen_used: f64,
+}
+
+impl RenderedPlan {
+    pub fn new(plan: &PlanResult, units: UnitSystem) -> Self {
+        let segments = plan
+            .segments()
+            .iter()
+            .map(|(segment, gas)| RenderedSegment {
+                segment_type: segment.segment_type(),
+                start_depth: units.depth(segment.start_depth()),
+                end_depth: units.depth(segment.end_depth()),
+                time: *segment.time(),
+                gas: *gas,
+            })
+            .collect();
+
+        let gas_used = plan
+            .gas_used()
+            .iter()
+            .map(|(gas, volume)| (*gas, units.volume(*volume)))
+            .collect();
+
+        Self {
+            units,
+            segments,
+            gas_used,
+            oxygen_used: units.volume(plan.oxygen_used()),
+        }
+    }
+
+    pub fn units(&self) -> UnitSystem {
+        self.units
+    }
+    pub fn segments(&self) -> &Vec<RenderedSegment> {
+        &self.segments
+    }
+    /// Volume of each gas used, in litres or cubic feet.
+    pub fn gas_used(&self) -> &HashMap<Gas, f64> {
+        &self.gas_used
+    }
+    /// Volume of oxygen added to a closed circuit loop, in litres or cubic feet.
+    pub fn oxygen_used(&self) -> f64 {
+        self.oxygen_used
+    }
+}
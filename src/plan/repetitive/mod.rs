@@ -0,0 +1,224 @@
+use crate::cylinder::Cylinder;
+use crate::deco::DecoAlgorithm;
+use crate::gas::Gas;
+use crate::oxygen::OxygenExposure;
+use crate::parameter::Parameters;
+use crate::plan::open_circuit::OpenCircuit;
+use crate::plan::plan_result::PlanResult;
+use crate::plan::{DivePlan, PlanError};
+use crate::segment::{Segment, SegmentType};
+use crate::surface_interval::SurfaceInterval;
+use crate::units::depth::{Depth, SURFACE};
+use crate::util::time_taken;
+
+/// A step in a sequence of repetitive dives.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RepetitiveStep {
+    /// An open circuit dive, planned from the surface back to the surface.
+    Dive {
+        bottom_segments: Vec<(Segment, Gas)>,
+        deco_gases: Vec<(Gas, Option<Depth>)>,
+        cylinders: Vec<Cylinder>,
+    },
+    /// Time spent at the surface between dives.
+    SurfaceInterval(SurfaceInterval),
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RepetitiveDives<T>
+where
+    T: DecoAlgorithm + Clone,
+{
+    deco: T,
+    steps: Vec<RepetitiveStep>,
+    parameters: Parameters,
+}
+
+impl<T> RepetitiveDives<T>
+where
+    T: DecoAlgorithm + Clone,
+{
+    pub fn new(deco: T, steps: &[RepetitiveStep], parameters: Parameters) -> Self {
+        Self {
+            deco,
+            steps: steps.to_vec(),
+            parameters,
+        }
+    }
+
+    /// Plan every dive in order, carrying tissues and oxygen exposure from one dive to the next.
+    /// Returns one result per dive.
+    /// # Errors
+    /// Returns an error if any dive cannot be planned.
+    pub fn get_plans(self) -> Result<Vec<PlanResult>, PlanError> {
+        let environment = self.parameters.environment();
+        let mut deco = self.deco;
+        let mut residual = OxygenExposure::default();
+        let mut plans = Vec::new();
+
+        for step in self.steps {
+            match step {
+                RepetitiveStep::Dive {
+                    bottom_segments,
+                    deco_gases,
+                    cylinders,
+                } => {
+                    let plan = OpenCircuit::new(
+                        deco.clone(),
+                        &bottom_segments,
+                        &deco_gases,
+                        &cylinders,
+                        self.parameters,
+                    )?
+                    .get_plan()?;
+
+                    // Replay the plan to recover the state of the model at the end of the dive
+                    for (segment, gas) in plan
+                        .segments()
+                        .iter()
+                        .filter(|(segment, _)| segment.segment_type() != SegmentType::NoDeco)
+                    {
+                        deco = deco.add_segment(segment, gas, environment)?;
+                    }
+
+                    let plan = PlanResult::new(
+                        plan.end_tissue(),
+                        plan.segments(),
+                        plan.gas_used(),
+                        plan.segment_oxygen_exposure(),
+                        residual,
+                        plan.cylinders(),
+                        self.parameters,
+                    )
+                    .with_gas_shortfalls(plan.gas_shortfalls())
+                    .with_oxygen_used(plan.oxygen_used());
+                    residual = plan.oxygen_exposure();
+
+                    // Surface the diver on the last gas breathed
+                    if let Some((_, gas)) = plan.segments().last() {
+                        if deco.model_depth() > SURFACE {
+                            let ascent = Segment::new(
+                                SegmentType::AscDesc,
+                                deco.model_depth(),
+                                SURFACE,
+                                time_taken(
+                                    self.parameters.ascent_rate(),
+                                    deco.model_depth(),
+                                    SURFACE,
+                                ),
+                                self.parameters.ascent_rate(),
+                                self.parameters.descent_rate(),
+                            )?;
+                            deco = deco.add_segment(&ascent, gas, environment)?;
+                            residual += OxygenExposure::from_segment(&ascent, gas, environment);
+                        }
+                    }
+                    deco = deco.reset_dive_state();
+
+                    plans.push(plan);
+                }
+                RepetitiveStep::SurfaceInterval(interval) => {
+                    deco = interval.apply(deco, environment)?;
+                    residual = residual.after_surface_interval(interval.duration());
+                }
+            }
+        }
+
+        Ok(plans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deco::zhl16::builder::ZHL16Builder;
+    use crate::environment::Environment;
+    use crate::gas::GasModel;
+    use crate::limits::Limits;
+    use crate::units::altitude::Altitude;
+    use crate::units::consumption_rate::GasConsumptionRate;
+    use crate::units::rate::Rate;
+    use crate::units::water_density::SALTWATER;
+    use time::Duration;
+
+    fn parameters() -> Parameters {
+        Parameters::new(
+            Rate::from_metres_per_minute(-10.0),
+            Rate::from_metres_per_minute(20.0),
+            Environment::new(SALTWATER, Altitude(0)),
+            GasConsumptionRate::from_litres_per_minute(20.0),
+            GasConsumptionRate::from_litres_per_minute(15.0),
+            GasModel::Ideal,
+            Limits::default(),
+        )
+    }
+
+    fn dive(depth: f64, minutes: i64) -> RepetitiveStep {
+        let parameters = parameters();
+        let depth = Depth::from_metres(depth);
+        let bottom = Segment::new(
+            SegmentType::Bottom,
+            depth,
+            depth,
+            Duration::minutes(minutes),
+            parameters.ascent_rate(),
+            parameters.descent_rate(),
+        )
+        .unwrap();
+
+        RepetitiveStep::Dive {
+            bottom_segments: vec![(bottom, Gas::new(21, 0, 79).unwrap())],
+            deco_gases: Vec::new(),
+            cylinders: Vec::new(),
+        }
+    }
+
+    fn total_time(plan: &PlanResult, segment_type: SegmentType) -> Duration {
+        plan.segments()
+            .iter()
+            .filter(|(segment, _)| segment.segment_type() == segment_type)
+            .fold(Duration::zero(), |acc, (segment, _)| acc + *segment.time())
+    }
+
+    #[test]
+    fn second_dive_has_shorter_ndl() {
+        let plans = RepetitiveDives::new(
+            ZHL16Builder::new().finish(),
+            &[
+                dive(20.0, 20),
+                RepetitiveStep::SurfaceInterval(SurfaceInterval::air(Duration::minutes(30))),
+                dive(20.0, 20),
+            ],
+            parameters(),
+        )
+        .get_plans()
+        .unwrap();
+
+        let first = total_time(&plans[0], SegmentType::NoDeco);
+        let second = total_time(&plans[1], SegmentType::NoDeco);
+        assert!(first > Duration::zero());
+        assert!(second < first, "{:?} {:?}", second, first);
+    }
+
+    #[test]
+    fn second_dive_has_more_deco() {
+        let plans = RepetitiveDives::new(
+            ZHL16Builder::new().finish(),
+            &[
+                dive(36.0, 25),
+                RepetitiveStep::SurfaceInterval(SurfaceInterval::air(Duration::minutes(45))),
+                dive(36.0, 25),
+            ],
+            parameters(),
+        )
+        .get_plans()
+        .unwrap();
+
+        let first = total_time(&plans[0], SegmentType::DecoStop);
+        let second = total_time(&plans[1], SegmentType::DecoStop);
+        assert!(first > Duration::zero());
+        assert!(second > first, "{:?} {:?}", second, first);
+    }
+}
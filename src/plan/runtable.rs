@@ -0,0 +1,450 @@
+use crate::gas::Gas;
+use crate::parameter::Parameters;
+use crate::plan::plan_result::PlanResult;
+use crate::segment::SegmentType;
+use crate::units::consumption::GasConsumption;
+use crate::units::depth::{Depth, SURFACE};
+use crate::units::pressure::Pressure;
+use crate::units::unit_system::UnitSystem;
+use time::Duration;
+
+/// Index of the gas column, which is left aligned. Every other column is numeric.
+const GAS_COLUMN: usize = 3;
+
+/// Output format of a [`Runtable`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RuntableFormat {
+    /// Columns aligned with spaces, for printing or wet notes.
+    Text,
+    /// GitHub flavoured Markdown table.
+    Markdown,
+    /// Comma separated values with a header row.
+    Csv,
+}
+
+/// Options for building a [`Runtable`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuntableOptions {
+    units: UnitSystem,
+    round_runtimes: bool,
+}
+
+impl RuntableOptions {
+    /// # Arguments
+    /// * `units` - Unit system used for depths and volumes
+    /// * `round_runtimes` - Whether to round each runtime up to a whole minute. Stop times are
+    ///   then the difference between consecutive rounded runtimes.
+    pub fn new(units: UnitSystem, round_runtimes: bool) -> Self {
+        Self {
+            units,
+            round_runtimes,
+        }
+    }
+
+    pub fn units(&self) -> UnitSystem {
+        self.units
+    }
+
+    pub fn round_runtimes(&self) -> bool {
+        self.round_runtimes
+    }
+}
+
+/// A level of a runtable, including the travel from the previous level.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuntableRow {
+    segment_type: SegmentType,
+    depth: Depth,
+    stop_time: Duration,
+    runtime: Duration,
+    gas: Gas,
+    pp_o2: Pressure,
+    end: Depth,
+    gas_used: GasConsumption,
+}
+
+impl RuntableRow {
+    /// Type of the segment at this level. [`SegmentType::AscDesc`] for the final ascent, or for
+    /// travel before a gas switch.
+    pub fn segment_type(&self) -> SegmentType {
+        self.segment_type
+    }
+    pub fn depth(&self) -> Depth {
+        self.depth
+    }
+    /// Time at this level, including the travel from the previous level.
+    pub fn stop_time(&self) -> Duration {
+        self.stop_time
+    }
+    /// Time from the start of the dive to leaving this level.
+    pub fn runtime(&self) -> Duration {
+        self.runtime
+    }
+    pub fn gas(&self) -> Gas {
+        self.gas
+    }
+    pub fn pp_o2(&self) -> Pressure {
+        self.pp_o2
+    }
+    /// Equivalent narcotic depth at this level.
+    pub fn end(&self) -> Depth {
+        self.end
+    }
+    /// Gas used at this level and during the travel to it.
+    pub fn gas_used(&self) -> GasConsumption {
+        self.gas_used
+    }
+}
+
+/// A classic runtable of a plan, with one row per level.
+/// Travel segments are folded into the following level, unless the diver switches gas there.
+/// Travel on a different gas gets its own row, so its gas used is booked against the gas breathed.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Runtable {
+    options: RuntableOptions,
+    rows: Vec<RuntableRow>,
+}
+
+impl Runtable {
+    /// # Arguments
+    /// * `plan` - Plan to tabulate
+    /// * `parameters` - Parameters the plan was calculated with, for gas used and ppO2
+    /// * `options` - Units and rounding of the table
+    pub fn new(plan: &PlanResult, parameters: Parameters, options: RuntableOptions) -> Self {
+        let environment = parameters.environment();
+        let mut rows: Vec<RuntableRow> = Vec::new();
+        let mut travel_time = Duration::zero();
+        let mut travel_gas_used = GasConsumption::default();
+        let mut runtime = Duration::zero();
+        let mut rounded_runtime = Duration::zero();
+
+        let segments = plan
+            .segments()
+            .iter()
+            .filter(|(segment, _)| segment.segment_type() != SegmentType::NoDeco)
+            .collect::<Vec<_>>();
+
+        for (index, (segment, gas)) in segments.iter().enumerate() {
+            let sac_rate = match segment.segment_type() {
+                SegmentType::DecoStop => parameters.sac_deco(),
+                _ => parameters.sac_bottom(),
+            };
+            let gas_used = segment.gas_consumed(sac_rate, environment);
+            runtime += *segment.time();
+
+            // Fold travel into the next level, unless it is the final ascent or the diver switches
+            // gas on arrival
+            let next_gas = segments.get(index + 1).map(|(_, next_gas)| next_gas);
+            if segment.segment_type() == SegmentType::AscDesc && next_gas == Some(gas) {
+                travel_time += *segment.time();
+                travel_gas_used += gas_used;
+                continue;
+            }
+
+            let depth = segment.end_depth();
+            let (stop_time, row_runtime) = if options.round_runtimes {
+                let previous = rounded_runtime;
+                rounded_runtime = round_up_to_minute(runtime).max(previous);
+                (rounded_runtime - previous, rounded_runtime)
+            } else {
+                (travel_time + *segment.time(), runtime)
+            };
+
+            rows.push(RuntableRow {
+                segment_type: segment.segment_type(),
+                depth,
+                stop_time,
+                runtime: row_runtime,
+                gas: *gas,
+                pp_o2: gas.pp_o2(depth, environment),
+                end: gas.equivalent_narcotic_depth(depth).max(SURFACE),
+                gas_used: travel_gas_used + gas_used,
+            });
+
+            travel_time = Duration::zero();
+            travel_gas_used = GasConsumption::default();
+        }
+
+        Self { options, rows }
+    }
+
+    pub fn options(&self) -> RuntableOptions {
+        self.options
+    }
+
+    pub fn rows(&self) -> &Vec<RuntableRow> {
+        &self.rows
+    }
+
+    /// Write the table in the given format. Every line, including the last, ends with a newline.
+    pub fn format(&self, format: RuntableFormat) -> String {
+        let header = self.header();
+        let cells = self
+            .rows
+            .iter()
+            .map(|row| self.cells(row))
+            .collect::<Vec<_>>();
+
+        match format {
+            RuntableFormat::Text => {
+                let mut widths = header.iter().map(|title| title.len()).collect::<Vec<_>>();
+                for row in &cells {
+                    for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                        *width = (*width).max(cell.len());
+                    }
+                }
+                let line = |row: &[String]| {
+                    let mut line = row
+                        .iter()
+                        .zip(widths.iter())
+                        .enumerate()
+                        .map(|(column, (cell, width))| {
+                            if column == GAS_COLUMN {
+                                format!("{:<width$}", cell, width = width)
+                            } else {
+                                format!("{:>width$}", cell, width = width)
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join("  ");
+                    line.push('\n');
+                    line
+                };
+
+                let mut table = line(&header);
+                table.push_str(&line(
+                    &widths
+                        .iter()
+                        .map(|width| "-".repeat(*width))
+                        .collect::<Vec<_>>(),
+                ));
+                for row in &cells {
+                    table.push_str(&line(row));
+                }
+                table
+            }
+            RuntableFormat::Markdown => {
+                let line = |row: &[String]| format!("| {} |\n", row.join(" | "));
+
+                let mut table = line(&header);
+                table.push_str(&line(
+                    &(0..header.len())
+                        .map(|column| {
+                            if column == GAS_COLUMN {
+                                String::from("---")
+                            } else {
+                                String::from("---:")
+                            }
+                        })
+                        .collect::<Vec<_>>(),
+                ));
+                for row in &cells {
+                    table.push_str(&line(row));
+                }
+                table
+            }
+            RuntableFormat::Csv => {
+                let line = |row: &[String]| format!("{}\n", row.join(","));
+
+                let mut table = line(&header);
+                for row in &cells {
+                    table.push_str(&line(row));
+                }
+                table
+            }
+        }
+    }
+
+    fn header(&self) -> Vec<String> {
+        let units = self.options.units;
+        vec![
+            format!("Depth ({})", units.depth_unit()),
+            String::from("Stop"),
+            String::from("Runtime"),
+            String::from("Gas"),
+            String::from("ppO2"),
+            format!("END ({})", units.depth_unit()),
+            format!("Gas used ({})", units.volume_unit()),
+        ]
+    }
+
+    fn cells(&self, row: &RuntableRow) -> Vec<String> {
+        let units = self.options.units;
+        vec![
+            format!("{:.0}", units.depth(row.depth)),
+            self.format_time(row.stop_time),
+            self.format_time(row.runtime),
+            row.gas.to_string(),
+            format!("{:.2}", row.pp_o2.0),
+            format!("{:.0}", units.depth(row.end)),
+            format!("{:.0}", units.volume(row.gas_used)),
+        ]
+    }
+
+    /// Whole minutes when runtimes are rounded, otherwise minutes and seconds.
+    fn format_time(&self, time: Duration) -> String {
+        if self.options.round_runtimes {
+            format!("{}", time.whole_minutes())
+        } else {
+            let seconds = time.whole_seconds();
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        }
+    }
+}
+
+fn round_up_to_minute(time: Duration) -> Duration {
+    Duration::minutes((time.whole_seconds() + 59) / 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Environment;
+    use crate::gas::GasModel;
+    use crate::limits::Limits;
+    use crate::oxygen::OxygenExposure;
+    use crate::segment::Segment;
+    use crate::tissue::Tissue;
+    use crate::units::altitude::Altitude;
+    use crate::units::consumption_rate::GasConsumptionRate;
+    use crate::units::rate::Rate;
+    use crate::units::water_density::SALTWATER;
+    use std::collections::HashMap;
+
+    fn parameters() -> Parameters {
+        Parameters::new(
+            Rate::from_metres_per_minute(-10.0),
+            Rate::from_metres_per_minute(20.0),
+            Environment::new(SALTWATER, Altitude(0)),
+            GasConsumptionRate::from_litres_per_minute(20.0),
+            GasConsumptionRate::from_litres_per_minute(15.0),
+            GasModel::Ideal,
+            Limits::default(),
+        )
+    }
+
+    /// 30 m on air, then stops at 21 m and 18 m on EAN50.
+    fn runtable(round_runtimes: bool) -> Runtable {
+        let parameters = parameters();
+        let air = Gas::new(21, 0, 79).unwrap();
+        let ean50 = Gas::new(50, 0, 50).unwrap();
+        let segment = |segment_type, start: f64, end: f64, seconds| {
+            Segment::new(
+                segment_type,
+                Depth::from_metres(start),
+                Depth::from_metres(end),
+                Duration::seconds(seconds),
+                parameters.ascent_rate(),
+                parameters.descent_rate(),
+            )
+            .unwrap()
+        };
+        let segments = [
+            (segment(SegmentType::AscDesc, 0.0, 30.0, 90), air),
+            (segment(SegmentType::Bottom, 30.0, 30.0, 1200), air),
+            (segment(SegmentType::AscDesc, 30.0, 21.0, 54), air),
+            (segment(SegmentType::DecoStop, 21.0, 21.0, 120), ean50),
+            (segment(SegmentType::AscDesc, 21.0, 18.0, 18), ean50),
+            (segment(SegmentType::DecoStop, 18.0, 18.0, 180), ean50),
+            (segment(SegmentType::AscDesc, 18.0, 0.0, 108), ean50),
+        ];
+        let plan = PlanResult::new(
+            Tissue::default(),
+            &segments,
+            &HashMap::new(),
+            &[OxygenExposure::default(); 7],
+            OxygenExposure::default(),
+            &[],
+            parameters,
+        );
+
+        Runtable::new(
+            &plan,
+            parameters,
+            RuntableOptions::new(UnitSystem::Metric, round_runtimes),
+        )
+    }
+
+    fn summary(runtable: &Runtable) -> Vec<(SegmentType, f64, i64, i64, Gas)> {
+        runtable
+            .rows()
+            .iter()
+            .map(|row| {
+                (
+                    row.segment_type(),
+                    row.depth().metres(),
+                    row.stop_time().whole_seconds(),
+                    row.runtime().whole_seconds(),
+                    row.gas(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn travel_before_gas_switch_has_its_own_row() {
+        let air = Gas::new(21, 0, 79).unwrap();
+        let ean50 = Gas::new(50, 0, 50).unwrap();
+
+        assert_eq!(
+            summary(&runtable(false)),
+            vec![
+                (SegmentType::Bottom, 30.0, 1290, 1290, air),
+                (SegmentType::AscDesc, 21.0, 54, 1344, air),
+                (SegmentType::DecoStop, 21.0, 120, 1464, ean50),
+                (SegmentType::DecoStop, 18.0, 198, 1662, ean50),
+                (SegmentType::AscDesc, 0.0, 108, 1770, ean50),
+            ]
+        );
+    }
+
+    #[test]
+    fn travel_gas_is_booked_against_the_gas_breathed() {
+        let runtable = runtable(false);
+        let environment = parameters().environment();
+        let travel = Segment::new(
+            SegmentType::AscDesc,
+            Depth::from_metres(30.0),
+            Depth::from_metres(21.0),
+            Duration::seconds(54),
+            parameters().ascent_rate(),
+            parameters().descent_rate(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            runtable.rows()[1].gas_used(),
+            travel.gas_consumed(parameters().sac_bottom(), environment)
+        );
+    }
+
+    #[test]
+    fn runtimes_are_rounded_up_to_whole_minutes() {
+        // Runtimes of 21:30, 22:24, 24:24, 27:42 and 29:30
+        let rows = summary(&runtable(true))
+            .into_iter()
+            .map(|(_, depth, stop, runtime, _)| (depth, stop / 60, runtime / 60))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rows,
+            vec![
+                (30.0, 22, 22),
+                (21.0, 1, 23),
+                (21.0, 2, 25),
+                (18.0, 3, 28),
+                (0.0, 2, 30),
+            ]
+        );
+        assert!(runtable(true)
+            .format(RuntableFormat::Csv)
+            .lines()
+            .nth(3)
+            .unwrap()
+            .starts_with("21,2,25,EAN50,"));
+    }
+}
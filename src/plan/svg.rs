@@ -0,0 +1,531 @@
+use crate::deco::{DecoAlgorithm, DecoError, TISSUE_COUNT};
+use crate::environment::Environment;
+use crate::gas::Gas;
+use crate::plan::plan_result::PlanResult;
+use crate::segment::{Segment, SegmentType};
+use crate::units::depth::Depth;
+use crate::units::unit_system::UnitSystem;
+use std::fmt::Write;
+use time::Duration;
+
+/// Colours given to each gas, in the order the gases first appear in the plan.
+const GAS_COLOURS: [&str; 8] = [
+    "#1f77b4", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b", "#e377c2", "#17becf", "#bcbd22",
+];
+
+/// Colour of the ceiling curve.
+const CEILING_COLOUR: &str = "#d62728";
+
+/// Space around the plot area for axes and labels (measured in px).
+const MARGIN_LEFT: f64 = 60.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 20.0;
+const MARGIN_BOTTOM: f64 = 40.0;
+
+/// Height of each compartment row in the saturation strip (measured in px).
+const SATURATION_ROW_HEIGHT: f64 = 5.0;
+
+/// Longest step used when replaying the plan through the decompression model.
+const REPLAY_STEP: Duration = Duration::minute();
+
+/// Options for drawing a plan as an SVG profile chart.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SvgOptions {
+    width: u32,
+    height: u32,
+    units: UnitSystem,
+    ceiling: bool,
+    saturation: bool,
+}
+
+impl SvgOptions {
+    /// # Arguments
+    /// * `width` - Width of the image (measured in px)
+    /// * `height` - Height of the image (measured in px)
+    /// * `units` - Unit system used for the depth axis
+    /// * `ceiling` - Whether to overlay the ascent ceiling
+    /// * `saturation` - Whether to draw the supersaturation of each compartment below the profile
+    pub fn new(
+        width: u32,
+        height: u32,
+        units: UnitSystem,
+        ceiling: bool,
+        saturation: bool,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            units,
+            ceiling,
+            saturation,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn units(&self) -> UnitSystem {
+        self.units
+    }
+
+    pub fn ceiling(&self) -> bool {
+        self.ceiling
+    }
+
+    pub fn saturation(&self) -> bool {
+        self.saturation
+    }
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self::new(800, 400, UnitSystem::Metric, true, false)
+    }
+}
+
+/// State of the decompression model at a point in the replayed plan.
+struct ReplayPoint {
+    runtime: f64,
+    ceiling: f64,
+    supersaturation: [f64; TISSUE_COUNT],
+}
+
+/// Draw a plan as an SVG chart of depth against runtime.
+/// Segments are coloured by gas, gas switches are marked, and stops are labelled with their
+/// duration. The ceiling and compartment supersaturation are calculated by replaying the plan
+/// through `deco` on the gas of each segment.
+/// # Arguments
+/// * `plan` - Plan to draw
+/// * `deco` - Decompression model at the start of the dive, used for the overlays
+/// * `environment` - Environment of the dive
+/// * `options` - Size, units and overlays of the chart
+/// # Errors
+/// Returns an error if the plan cannot be replayed through the decompression model.
+pub fn render<T: DecoAlgorithm>(
+    plan: &PlanResult,
+    deco: T,
+    environment: Environment,
+    options: SvgOptions,
+) -> Result<String, DecoError> {
+    let units = options.units;
+    let segments = plan
+        .segments()
+        .iter()
+        .filter(|(segment, _)| segment.segment_type() != SegmentType::NoDeco)
+        .collect::<Vec<_>>();
+
+    let replay = if options.ceiling || options.saturation {
+        replay(&segments, deco, environment, units)?
+    } else {
+        Vec::new()
+    };
+
+    let total_runtime = segments
+        .iter()
+        .map(|(segment, _)| minutes(*segment.time()))
+        .sum::<f64>()
+        .max(1.0);
+    let max_depth = segments
+        .iter()
+        .map(|(segment, _)| units.depth(segment.start_depth().max(segment.end_depth())))
+        .chain(replay.iter().map(|point| point.ceiling))
+        .fold(1.0, f64::max);
+
+    let time_step = tick_step(total_runtime);
+    let depth_step = tick_step(max_depth);
+    let time_axis = (total_runtime / time_step).ceil() * time_step;
+    let depth_axis = (max_depth / depth_step).ceil() * depth_step;
+
+    let width = options.width as f64;
+    let height = options.height as f64;
+    let strip_height = if options.saturation {
+        SATURATION_ROW_HEIGHT * TISSUE_COUNT as f64 + MARGIN_BOTTOM
+    } else {
+        0.0
+    };
+    let plot_width = (width - MARGIN_LEFT - MARGIN_RIGHT).max(1.0);
+    let plot_height = (height - MARGIN_TOP - MARGIN_BOTTOM - strip_height).max(1.0);
+
+    let x = |runtime: f64| MARGIN_LEFT + runtime / time_axis * plot_width;
+    let y = |depth: f64| MARGIN_TOP + depth / depth_axis * plot_height;
+
+    let mut svg = String::new();
+    // Writing to a String cannot fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" font-family="sans-serif" font-size="11">"#,
+        options.width, options.height, options.width, options.height
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{}" height="{}" fill="white"/>"#,
+        options.width, options.height
+    );
+
+    // Grid and axes
+    let mut tick = 0.0;
+    while tick <= time_axis {
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{0:.1}" y1="{1:.1}" x2="{0:.1}" y2="{2:.1}" stroke="#e0e0e0"/><text x="{0:.1}" y="{3:.1}" text-anchor="middle">{4}</text>"##,
+            x(tick),
+            y(0.0),
+            y(depth_axis),
+            y(depth_axis) + 15.0,
+            tick
+        );
+        tick += time_step;
+    }
+    let mut tick = 0.0;
+    while tick <= depth_axis {
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{0:.1}" y1="{1:.1}" x2="{2:.1}" y2="{1:.1}" stroke="#e0e0e0"/><text x="{3:.1}" y="{4:.1}" text-anchor="end">{5}</text>"##,
+            x(0.0),
+            y(tick),
+            x(time_axis),
+            x(0.0) - 5.0,
+            y(tick) + 4.0,
+            tick
+        );
+        tick += depth_step;
+    }
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="none" stroke="black"/>"#,
+        x(0.0),
+        y(0.0),
+        plot_width,
+        plot_height
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">Runtime (min)</text>"#,
+        x(time_axis / 2.0),
+        y(depth_axis) + 32.0
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text x="15" y="{0:.1}" text-anchor="middle" transform="rotate(-90 15 {0:.1})">Depth ({1})</text>"#,
+        y(depth_axis / 2.0),
+        units.depth_unit()
+    );
+
+    // Profile, gas switches and stops
+    let mut gases: Vec<Gas> = Vec::new();
+    let mut runtime = 0.0;
+    let mut previous_gas: Option<Gas> = None;
+    for (segment, gas) in &segments {
+        if !gases.contains(gas) {
+            gases.push(*gas);
+        }
+        let colour = gas_colour(&gases, gas);
+        let start = runtime;
+        runtime += minutes(*segment.time());
+        let start_depth = units.depth(segment.start_depth());
+        let end_depth = units.depth(segment.end_depth());
+
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="2"/>"#,
+            x(start),
+            y(start_depth),
+            x(runtime),
+            y(end_depth),
+            colour
+        );
+
+        if matches!(previous_gas, Some(previous) if previous != *gas) {
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{0:.1}" cy="{1:.1}" r="4" fill="{2}"/><text x="{3:.1}" y="{4:.1}" fill="{2}">{5}</text>"#,
+                x(start),
+                y(start_depth),
+                colour,
+                x(start) + 6.0,
+                y(start_depth) + 14.0,
+                gas
+            );
+        }
+        previous_gas = Some(*gas);
+
+        if segment.segment_type() == SegmentType::DecoStop {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="middle" font-size="9">{} min</text>"#,
+                x((start + runtime) / 2.0),
+                y(start_depth) - 4.0,
+                minutes(*segment.time()).ceil()
+            );
+        }
+    }
+
+    // Ceiling
+    if options.ceiling && !replay.is_empty() {
+        let points = replay
+            .iter()
+            .map(|point| format!("{:.1},{:.1}", x(point.runtime), y(point.ceiling)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-dasharray="4 2"/>"#,
+            points, CEILING_COLOUR
+        );
+    }
+
+    // Compartment supersaturation, one row per compartment
+    if options.saturation {
+        let top = y(depth_axis) + MARGIN_BOTTOM;
+        for window in replay.windows(2) {
+            let (from, to) = (&window[0], &window[1]);
+            for (compartment, supersaturation) in to.supersaturation.iter().enumerate() {
+                let _ = writeln!(
+                    svg,
+                    r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"/>"#,
+                    x(from.runtime),
+                    top + compartment as f64 * SATURATION_ROW_HEIGHT,
+                    x(to.runtime) - x(from.runtime),
+                    SATURATION_ROW_HEIGHT,
+                    saturation_colour(*supersaturation)
+                );
+            }
+        }
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="end" font-size="9">1</text><text x="{:.1}" y="{:.1}" text-anchor="end" font-size="9">{}</text>"#,
+            x(0.0) - 5.0,
+            top + SATURATION_ROW_HEIGHT,
+            x(0.0) - 5.0,
+            top + TISSUE_COUNT as f64 * SATURATION_ROW_HEIGHT,
+            TISSUE_COUNT
+        );
+    }
+
+    // Legend
+    let mut legend = gases
+        .iter()
+        .map(|gas| (gas.to_string(), gas_colour(&gases, gas)))
+        .collect::<Vec<_>>();
+    if options.ceiling && !replay.is_empty() {
+        legend.push((String::from("Ceiling"), CEILING_COLOUR));
+    }
+    for (index, (label, colour)) in legend.iter().enumerate() {
+        let entry_y = y(0.0) + 10.0 + index as f64 * 14.0;
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{:.1}" y="{:.1}" width="10" height="10" fill="{}"/><text x="{:.1}" y="{:.1}">{}</text>"#,
+            x(time_axis) - 80.0,
+            entry_y,
+            colour,
+            x(time_axis) - 66.0,
+            entry_y + 9.0,
+            label
+        );
+    }
+
+    svg.push_str("</svg>\n");
+
+    Ok(svg)
+}
+
+/// Replay the segments through `deco` in steps of at most [`REPLAY_STEP`], recording the
+/// ceiling (in `units`) and the supersaturation of each compartment after each step.
+fn replay<T: DecoAlgorithm>(
+    segments: &[&(Segment, Gas)],
+    mut deco: T,
+    environment: Environment,
+    units: UnitSystem,
+) -> Result<Vec<ReplayPoint>, DecoError> {
+    let record = |deco: &T, runtime: f64| {
+        let mut supersaturation = [0.0; TISSUE_COUNT];
+        for (value, compartment) in supersaturation
+            .iter_mut()
+            .zip(deco.compartments(environment).iter())
+        {
+            *value = compartment.supersaturation();
+        }
+        ReplayPoint {
+            runtime,
+            ceiling: units.depth(deco.ceiling_depth(environment)),
+            supersaturation,
+        }
+    };
+
+    let mut points = vec![record(&deco, 0.0)];
+    let mut runtime = Duration::zero();
+
+    for (segment, gas) in segments {
+        let total = segment.time().whole_seconds();
+        let steps = (total + REPLAY_STEP.whole_seconds() - 1) / REPLAY_STEP.whole_seconds();
+        let depth_at = |seconds: i64| {
+            let fraction = if total == 0 {
+                1.0
+            } else {
+                seconds as f64 / total as f64
+            };
+            Depth::from_metres(
+                segment.start_depth().metres()
+                    + (segment.end_depth().metres() - segment.start_depth().metres()) * fraction,
+            )
+        };
+
+        let mut elapsed = 0;
+        for _ in 0..steps.max(1) {
+            let next = (elapsed + REPLAY_STEP.whole_seconds()).min(total);
+            let (start_depth, end_depth) = match segment.segment_type() {
+                SegmentType::AscDesc => (depth_at(elapsed), depth_at(next)),
+                _ => (segment.start_depth(), segment.end_depth()),
+            };
+            let time = Duration::seconds(next - elapsed);
+            // A short depth change can round to the same depth at both ends of a step, which is
+            // not a valid segment. The diver barely moves, so the step is not replayed.
+            if let Ok(step) = Segment::new(
+                segment.segment_type(),
+                start_depth,
+                end_depth,
+                time,
+                segment.ascent_rate(),
+                segment.descent_rate(),
+            ) {
+                deco = deco.add_segment(&step, gas, environment)?;
+            }
+            runtime += time;
+            points.push(record(&deco, minutes(runtime)));
+            elapsed = next;
+        }
+    }
+
+    Ok(points)
+}
+
+fn minutes(duration: Duration) -> f64 {
+    duration.as_seconds_f64() / 60.0
+}
+
+/// Smallest of 1, 2 or 5 times a power of ten which divides `range` into at most ten ticks.
+fn tick_step(range: f64) -> f64 {
+    let mut magnitude = 1.0;
+    loop {
+        for step in [1.0, 2.0, 5.0].iter() {
+            if range / (step * magnitude) <= 10.0 {
+                return step * magnitude;
+            }
+        }
+        magnitude *= 10.0;
+    }
+}
+
+fn gas_colour(gases: &[Gas], gas: &Gas) -> &'static str {
+    let index = gases.iter().position(|g| g == gas).unwrap_or_default();
+    GAS_COLOURS[index % GAS_COLOURS.len()]
+}
+
+/// White at equilibrium, shading to blue for on-gassing and to red as the M-value is reached.
+fn saturation_colour(supersaturation: f64) -> String {
+    let (target, fraction) = if supersaturation < 0.0 {
+        ((31.0, 119.0, 180.0), (-supersaturation / 100.0).min(1.0))
+    } else {
+        ((214.0, 39.0, 40.0), (supersaturation / 100.0).min(1.0))
+    };
+    let blend = |channel: f64| (255.0 + (channel - 255.0) * fraction).round() as u8;
+
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        blend(target.0),
+        blend(target.1),
+        blend(target.2)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deco::zhl16::builder::ZHL16Builder;
+    use crate::gas::{Gas, GasModel};
+    use crate::limits::Limits;
+    use crate::oxygen::OxygenExposure;
+    use crate::parameter::Parameters;
+    use crate::segment::Segment;
+    use crate::tissue::Tissue;
+    use crate::units::altitude::Altitude;
+    use crate::units::consumption_rate::GasConsumptionRate;
+    use crate::units::depth::Depth;
+    use crate::units::rate::Rate;
+    use crate::units::water_density::SALTWATER;
+    use std::collections::HashMap;
+    use time::Duration;
+
+    fn environment() -> Environment {
+        Environment::new(SALTWATER, Altitude(0))
+    }
+
+    /// Render a plan with a bottom segment on the first gas and a stop on the second.
+    fn svg(bottom_gas: Gas, deco_gas: Gas) -> String {
+        let parameters = Parameters::new(
+            Rate::from_metres_per_minute(-10.0),
+            Rate::from_metres_per_minute(20.0),
+            environment(),
+            GasConsumptionRate::from_litres_per_minute(20.0),
+            GasConsumptionRate::from_litres_per_minute(15.0),
+            GasModel::Ideal,
+            Limits::default(),
+        );
+        let segment = |segment_type, start: f64, end: f64, minutes| {
+            Segment::new(
+                segment_type,
+                Depth::from_metres(start),
+                Depth::from_metres(end),
+                Duration::minutes(minutes),
+                parameters.ascent_rate(),
+                parameters.descent_rate(),
+            )
+            .unwrap()
+        };
+        let segments = [
+            (segment(SegmentType::Bottom, 30.0, 30.0, 20), bottom_gas),
+            (segment(SegmentType::AscDesc, 30.0, 21.0, 1), bottom_gas),
+            (segment(SegmentType::DecoStop, 21.0, 21.0, 2), deco_gas),
+        ];
+        let plan = PlanResult::new(
+            Tissue::default(),
+            &segments,
+            &HashMap::new(),
+            &[OxygenExposure::default(); 3],
+            OxygenExposure::default(),
+            &[],
+            parameters,
+        );
+
+        render(
+            &plan,
+            ZHL16Builder::new().finish(),
+            environment(),
+            SvgOptions::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn gas_switch_is_marked() {
+        let svg = svg(Gas::new(21, 0, 79).unwrap(), Gas::new(50, 0, 50).unwrap());
+
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert!(svg.contains(">EAN50</text>"));
+    }
+
+    #[test]
+    fn single_gas_has_no_switch() {
+        let air = Gas::new(21, 0, 79).unwrap();
+        let svg = svg(air, air);
+
+        assert_eq!(svg.matches("<circle").count(), 0);
+        assert!(svg.contains("2 min</text>"));
+    }
+}
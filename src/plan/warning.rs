@@ -0,0 +1,193 @@
+use crate::gas::Gas;
+use crate::parameter::Parameters;
+use crate::segment::{Segment, SegmentType};
+use crate::units::depth::Depth;
+use crate::units::pressure::{Pressure, PPO2_MAXIMUM_DECO, PPO2_MAXIMUM_DIVE, PPO2_MINIMUM};
+use crate::units::rate::Rate;
+use time::Duration;
+
+/// A problem found in a plan. Each warning refers to a segment by its index in
+/// [`crate::plan::plan_result::PlanResult::segments`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PlanWarning {
+    /// ppO2 at the shallowest point of the segment is below [`PPO2_MINIMUM`].
+    HypoxicGas { segment: usize, pp_o2: Pressure },
+    /// ppO2 at the deepest point of the segment is above [`PPO2_MAXIMUM_DIVE`]
+    /// (or [`PPO2_MAXIMUM_DECO`] on ascents and decompression stops).
+    HighPpO2 { segment: usize, pp_o2: Pressure },
+    /// Equivalent narcotic depth at the deepest point of the segment is above the configured limit.
+    HighEnd { segment: usize, end: Depth },
+    /// The segment ascends faster than the configured ascent rate.
+    FastAscent { segment: usize, rate: Rate },
+}
+
+impl PlanWarning {
+    /// Check every segment of a plan against the limits in `parameters`.
+    pub fn find(segments: &[(Segment, Gas)], parameters: Parameters) -> Vec<Self> {
+        let environment = parameters.environment();
+        let mut warnings = Vec::new();
+
+        for (index, (segment, gas)) in segments
+            .iter()
+            .enumerate()
+            .filter(|(_, (segment, _))| segment.segment_type() != SegmentType::NoDeco)
+        {
+            let deepest = segment.start_depth().max(segment.end_depth());
+            let shallowest = segment.start_depth().min(segment.end_depth());
+            // Ascents and stops are held to the decompression limit
+            let max_pp_o2 = if segment.end_depth() < segment.start_depth()
+                || segment.segment_type() == SegmentType::DecoStop
+            {
+                PPO2_MAXIMUM_DECO
+            } else {
+                PPO2_MAXIMUM_DIVE
+            };
+            // ppO2 is lowest at the shallowest point of the segment and highest at the deepest
+            let pp_o2 = gas.pp_o2(shallowest, environment);
+            if pp_o2 < PPO2_MINIMUM {
+                warnings.push(PlanWarning::HypoxicGas {
+                    segment: index,
+                    pp_o2,
+                });
+            }
+
+            let pp_o2 = gas.pp_o2(deepest, environment);
+            if pp_o2 > max_pp_o2 {
+                warnings.push(PlanWarning::HighPpO2 {
+                    segment: index,
+                    pp_o2,
+                });
+            }
+
+            let end = gas.equivalent_narcotic_depth(deepest);
+            if end > parameters.limits().max_end() {
+                warnings.push(PlanWarning::HighEnd {
+                    segment: index,
+                    end,
+                });
+            }
+
+            if segment.end_depth() < segment.start_depth() {
+                // Allow for segment times being truncated to whole seconds
+                let minutes = (*segment.time() + Duration::second()).as_seconds_f64() / 60.0;
+                let rate = segment.start_depth().delta(segment.end_depth()).metres() / minutes;
+                if rate > parameters.ascent_rate().metres_per_minute().abs() {
+                    warnings.push(PlanWarning::FastAscent {
+                        segment: index,
+                        rate: Rate::from_metres_per_minute(-rate),
+                    });
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Index of the offending segment.
+    pub fn segment(&self) -> usize {
+        match self {
+            PlanWarning::HypoxicGas { segment, .. }
+            | PlanWarning::HighPpO2 { segment, .. }
+            | PlanWarning::HighEnd { segment, .. }
+            | PlanWarning::FastAscent { segment, .. } => *segment,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Environment;
+    use crate::gas::GasModel;
+    use crate::limits::Limits;
+    use crate::units::altitude::Altitude;
+    use crate::units::consumption_rate::GasConsumptionRate;
+    use crate::units::depth::SURFACE;
+    use crate::units::water_density::SALTWATER;
+
+    fn parameters() -> Parameters {
+        Parameters::new(
+            Rate::from_metres_per_minute(-10.0),
+            Rate::from_metres_per_minute(20.0),
+            Environment::new(SALTWATER, Altitude(0)),
+            GasConsumptionRate::from_litres_per_minute(20.0),
+            GasConsumptionRate::from_litres_per_minute(15.0),
+            GasModel::Ideal,
+            Limits::default(),
+        )
+    }
+
+    fn segment(segment_type: SegmentType, start: f64, end: f64, minutes: i64) -> Segment {
+        let parameters = parameters();
+        Segment::new(
+            segment_type,
+            Depth::from_metres(start),
+            Depth::from_metres(end),
+            Duration::minutes(minutes),
+            parameters.ascent_rate(),
+            parameters.descent_rate(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn hypoxic_gas_at_the_surface() {
+        // 10% O2 is 0.10 bar at the start of the descent
+        let descent = segment(SegmentType::AscDesc, 0.0, 20.0, 1);
+        let warnings = PlanWarning::find(&[(descent, Gas::new(10, 50, 40).unwrap())], parameters());
+
+        assert_eq!(warnings.len(), 1);
+        match warnings[0] {
+            PlanWarning::HypoxicGas { segment, pp_o2 } => {
+                assert_eq!(segment, 0);
+                assert_eq!(
+                    pp_o2,
+                    Gas::new(10, 50, 40)
+                        .unwrap()
+                        .pp_o2(SURFACE, parameters().environment())
+                );
+            }
+            warning => panic!("unexpected warning {:?}", warning),
+        }
+    }
+
+    #[test]
+    fn hyperoxic_gas_at_depth() {
+        // EAN36 is 1.47 bar at 30 m
+        let bottom = segment(SegmentType::Bottom, 30.0, 30.0, 20);
+        let warnings = PlanWarning::find(&[(bottom, Gas::new(36, 0, 64).unwrap())], parameters());
+
+        assert_eq!(warnings.len(), 1);
+        match warnings[0] {
+            PlanWarning::HighPpO2 { segment, pp_o2 } => {
+                assert_eq!(segment, 0);
+                assert!(pp_o2 > PPO2_MAXIMUM_DIVE);
+            }
+            warning => panic!("unexpected warning {:?}", warning),
+        }
+    }
+
+    #[test]
+    fn deco_stop_is_held_to_the_deco_limit() {
+        // EAN50 is 1.52 bar at 21 m: above the bottom limit but within the deco limit
+        let stop = segment(SegmentType::DecoStop, 21.0, 21.0, 2);
+        let bottom = segment(SegmentType::Bottom, 21.0, 21.0, 2);
+        let ean50 = Gas::new(50, 0, 50).unwrap();
+
+        assert!(PlanWarning::find(&[(stop, ean50)], parameters()).is_empty());
+        assert_eq!(PlanWarning::find(&[(bottom, ean50)], parameters()).len(), 1);
+    }
+
+    #[test]
+    fn gas_in_range_has_no_warnings() {
+        let air = Gas::new(21, 0, 79).unwrap();
+        let segments = [
+            (segment(SegmentType::AscDesc, 0.0, 30.0, 2), air),
+            (segment(SegmentType::Bottom, 30.0, 30.0, 20), air),
+            (segment(SegmentType::AscDesc, 30.0, 0.0, 3), air),
+        ];
+
+        assert!(PlanWarning::find(&segments, parameters()).is_empty());
+    }
+}
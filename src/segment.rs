@@ -89,13 +89,13 @@ impl Segment {
         environment: Environment,
     ) -> GasConsumption {
         let pressure = match self.segment_type {
-            SegmentType::AscDesc => {
-                ((self.start_depth + self.end_depth) / Depth(2)).pressure(environment)
-            }
+            SegmentType::AscDesc => ((self.start_depth + self.end_depth) / 2).pressure(environment),
             _ => self.end_depth.pressure(environment),
         };
         GasConsumption(
-            (pressure.0 * (self.time.as_seconds_f64() / 60.0) * consumption_rate.0 as f64) as u32,
+            (pressure.0
+                * (self.time.as_seconds_f64() / 60.0)
+                * consumption_rate.litres_per_minute()) as u32,
         )
     }
 }
@@ -0,0 +1,55 @@
+use crate::deco::{DecoAlgorithm, DecoError};
+use crate::environment::Environment;
+use crate::gas::Gas;
+use crate::segment::{Segment, SegmentType};
+use crate::units::depth::SURFACE;
+use crate::units::rate::Rate;
+use time::Duration;
+
+/// Time spent breathing a gas at the surface, between dives.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SurfaceInterval {
+    duration: Duration,
+    gas: Gas,
+}
+
+impl SurfaceInterval {
+    pub fn new(duration: Duration, gas: Gas) -> Self {
+        Self { duration, gas }
+    }
+
+    /// A surface interval breathing air.
+    pub fn air(duration: Duration) -> Self {
+        Self::new(duration, Gas::new(21, 0, 79).unwrap())
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn gas(&self) -> Gas {
+        self.gas
+    }
+
+    /// Off-gas the diver at surface pressure for the duration of the interval.
+    /// The diver must already be at the surface.
+    pub fn apply<T: DecoAlgorithm>(
+        &self,
+        deco: T,
+        environment: Environment,
+    ) -> Result<T, DecoError> {
+        let segment = Segment::new(
+            SegmentType::Bottom,
+            SURFACE,
+            SURFACE,
+            self.duration,
+            Rate::default(),
+            Rate::default(),
+        )?;
+
+        Ok(deco
+            .add_segment(&segment, &self.gas, environment)?
+            .reset_dive_state())
+    }
+}
@@ -1,6 +1,10 @@
 use crate::deco::TISSUE_COUNT;
+use crate::environment::Environment;
 use crate::gas::Gas;
+use crate::segment::Segment;
+use crate::units::depth::Depth;
 use crate::units::pressure::{Pressure, WATER_VAPOUR_PRESSURE};
+use std::f64::consts::{E, LN_2};
 
 #[derive(Copy, Clone, Debug)]
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
@@ -14,6 +18,17 @@ impl Tissue {
         Self { p_n2, p_he }
     }
 
+    /// Tissues saturated with air at the surface pressure of an environment,
+    /// such as a diver living at altitude.
+    pub fn at_surface(environment: Environment) -> Self {
+        let air = Gas::new(21, 0, 79).unwrap();
+        let p_n2 = (environment.surface_pressure() - WATER_VAPOUR_PRESSURE) * Pressure(air.fr_n2());
+        Self {
+            p_n2: [p_n2; TISSUE_COUNT],
+            p_he: [Pressure::default(); TISSUE_COUNT],
+        }
+    }
+
     pub fn p_n2_mut(&mut self) -> &mut [Pressure; TISSUE_COUNT] {
         &mut self.p_n2
     }
@@ -29,6 +44,128 @@ impl Tissue {
     pub fn p_he(&self) -> &[Pressure; TISSUE_COUNT] {
         &self.p_he
     }
+
+    /// Load the compartments over a constant depth segment (Haldane equation).
+    pub(crate) fn add_flat_segment(
+        &mut self,
+        segment: &Segment,
+        gas: &Gas,
+        environment: Environment,
+        n2_half_lives: &[f64; TISSUE_COUNT],
+        he_half_lives: &[f64; TISSUE_COUNT],
+    ) {
+        let time = segment.time().whole_seconds() as f64 / 60.0;
+        let ambient = segment.end_depth().compensated_pressure(environment);
+
+        for (pressure, half_life) in self.p_n2.iter_mut().zip(n2_half_lives.iter()) {
+            let pi = ambient * Pressure(gas.fr_n2());
+            *pressure = Tissue::flat_loading(*pressure, pi, time, *half_life);
+        }
+
+        for (pressure, half_life) in self.p_he.iter_mut().zip(he_half_lives.iter()) {
+            let pi = ambient * Pressure(gas.fr_he());
+            *pressure = Tissue::flat_loading(*pressure, pi, time, *half_life);
+        }
+    }
+
+    /// Load the compartments over a changing depth segment (Schreiner equation).
+    pub(crate) fn add_depth_change_segment(
+        &mut self,
+        segment: &Segment,
+        gas: &Gas,
+        environment: Environment,
+        n2_half_lives: &[f64; TISSUE_COUNT],
+        he_half_lives: &[f64; TISSUE_COUNT],
+    ) {
+        let rate = if segment.start_depth() < segment.end_depth() {
+            segment.descent_rate()
+        } else {
+            segment.ascent_rate()
+        };
+
+        let time = segment.time().whole_seconds() as f64 / 60.0;
+        let initial_ambient = segment.start_depth().compensated_pressure(environment);
+        // Rate of change of ambient pressure (measured in bar/min)
+        let pressure_rate = rate.metres_per_minute() / environment.water_density().meters_per_bar();
+
+        // Nitrogen
+        for (pressure, half_life) in self.p_n2.iter_mut().zip(n2_half_lives.iter()) {
+            let pio = initial_ambient * Pressure(gas.fr_n2());
+            let r = pressure_rate * gas.fr_n2();
+            let k = LN_2 / half_life;
+
+            *pressure = Tissue::depth_change_loading(time, *pressure, pio, r, k);
+        }
+
+        // Helium
+        for (pressure, half_life) in self.p_he.iter_mut().zip(he_half_lives.iter()) {
+            let pio = initial_ambient * Pressure(gas.fr_he());
+            let r = pressure_rate * gas.fr_he();
+            let k = LN_2 / half_life;
+
+            *pressure = Tissue::depth_change_loading(time, *pressure, pio, r, k);
+        }
+    }
+
+    /// Load the compartments while breathing from a closed circuit loop, using the exact inert
+    /// fractions of the loop held at `setpoint` over `diluent`. Over a depth change the inspired
+    /// pressures are interpolated linearly between the ends of the segment.
+    pub(crate) fn add_loop_segment(
+        &mut self,
+        segment: &Segment,
+        diluent: &Gas,
+        setpoint: Pressure,
+        environment: Environment,
+        n2_half_lives: &[f64; TISSUE_COUNT],
+        he_half_lives: &[f64; TISSUE_COUNT],
+    ) {
+        let time = segment.time().whole_seconds() as f64 / 60.0;
+        let inspired = |depth: Depth| {
+            let (_, fr_he, fr_n2) = diluent.loop_fractions(setpoint, depth, environment);
+            let ambient = depth.compensated_pressure(environment);
+            (ambient * Pressure(fr_n2), ambient * Pressure(fr_he))
+        };
+        let (start_n2, start_he) = inspired(segment.start_depth());
+        let (end_n2, end_he) = inspired(segment.end_depth());
+        let rate = |start: Pressure, end: Pressure| {
+            if time > 0.0 {
+                (end - start).0 / time
+            } else {
+                0.0
+            }
+        };
+
+        // Nitrogen
+        let r = rate(start_n2, end_n2);
+        for (pressure, half_life) in self.p_n2.iter_mut().zip(n2_half_lives.iter()) {
+            let k = LN_2 / half_life;
+            *pressure = Tissue::depth_change_loading(time, *pressure, start_n2, r, k);
+        }
+
+        // Helium
+        let r = rate(start_he, end_he);
+        for (pressure, half_life) in self.p_he.iter_mut().zip(he_half_lives.iter()) {
+            let k = LN_2 / half_life;
+            *pressure = Tissue::depth_change_loading(time, *pressure, start_he, r, k);
+        }
+    }
+
+    fn flat_loading(po: Pressure, pi: Pressure, time: f64, half_life: f64) -> Pressure {
+        po + (pi - po) * Pressure(1.0 - (2.0_f64.powf(-time / half_life)))
+    }
+
+    fn depth_change_loading(
+        time: f64,
+        initial: Pressure,
+        initial_ambient: Pressure,
+        r: f64,
+        k: f64,
+    ) -> Pressure {
+        Pressure(
+            initial_ambient.0 + r * (time - (1.0 / k))
+                - ((initial_ambient.0 - initial.0 - (r / k)) * E.powf(-k * time)),
+        )
+    }
 }
 
 impl Default for Tissue {
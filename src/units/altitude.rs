@@ -5,9 +5,57 @@ use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Altitude(pub u32);
 
+/// Sea level pressure of the International Standard Atmosphere (measured in bar).
+const ISA_SEA_LEVEL_PRESSURE: f64 = 1.01325;
+
+/// Temperature lapse rate divided by sea level temperature in the ISA (measured in 1/m).
+const ISA_LAPSE_RATIO: f64 = 2.25577e-5;
+
+/// Exponent of the ISA barometric formula in the troposphere.
+const ISA_EXPONENT: f64 = 5.25588;
+
+/// Model used to convert an altitude to atmospheric pressure.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AtmosphereModel {
+    /// Simple exponential decay of pressure with altitude.
+    #[default]
+    Exponential,
+    /// Barometric formula of the International Standard Atmosphere (valid up to 11 km).
+    Isa,
+}
+
+impl AtmosphereModel {
+    pub fn pressure(&self, altitude: Altitude) -> Pressure {
+        let metres = altitude.0 as f64;
+        match self {
+            AtmosphereModel::Exponential => {
+                Pressure(101.325 * (-0.00012_f64 * metres).exp() / 100.0)
+            }
+            AtmosphereModel::Isa => Pressure(
+                ISA_SEA_LEVEL_PRESSURE * (1.0 - ISA_LAPSE_RATIO * metres).powf(ISA_EXPONENT),
+            ),
+        }
+    }
+
+    /// Altitude at which the model gives a pressure, rounded to the nearest metre.
+    /// Pressures above the sea level pressure give sea level.
+    pub fn altitude(&self, pressure: Pressure) -> Altitude {
+        let metres = match self {
+            AtmosphereModel::Exponential => (pressure.0 * 100.0 / 101.325).ln() / -0.00012,
+            AtmosphereModel::Isa => {
+                (1.0 - (pressure.0 / ISA_SEA_LEVEL_PRESSURE).powf(1.0 / ISA_EXPONENT))
+                    / ISA_LAPSE_RATIO
+            }
+        };
+        Altitude(metres.round().max(0.0) as u32)
+    }
+}
+
 impl Altitude {
+    /// Atmospheric pressure at this altitude, using the default (exponential) model.
     pub fn atmospheric_pressure(&self) -> Pressure {
-        Pressure(101.325 * (-0.00012_f64 * self.0 as f64).exp() / 100.0)
+        AtmosphereModel::default().pressure(*self)
     }
 }
 
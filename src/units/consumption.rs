@@ -1,10 +1,25 @@
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
+/// Number of litres in a cubic foot.
+pub const LITRES_PER_CUBIC_FOOT: f64 = 28.316_846_592;
+
 // Measured in litres
 #[derive(Copy, Clone, Debug, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GasConsumption(pub u32);
 
+impl GasConsumption {
+    /// Create a volume from a value in cubic feet, rounded to the nearest litre.
+    pub fn from_cubic_feet(cubic_feet: f64) -> Self {
+        GasConsumption((cubic_feet * LITRES_PER_CUBIC_FOOT).round() as u32)
+    }
+
+    /// Volume in cubic feet.
+    pub fn cubic_feet(&self) -> f64 {
+        self.0 as f64 / LITRES_PER_CUBIC_FOOT
+    }
+}
+
 impl Add for GasConsumption {
     type Output = GasConsumption;
 
@@ -1,4 +1,62 @@
-// Measured in bar/min
+use crate::units::consumption::LITRES_PER_CUBIC_FOOT;
+
+/// Number of millilitres in a litre.
+const MILLILITRES_PER_LITRE: f64 = 1000.0;
+
+/// Gas consumption rate, stored as a whole number of millilitres per minute so that rates given
+/// in cubic feet per minute keep their precision.
 #[derive(Copy, Clone, Debug, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct GasConsumptionRate(pub u32);
+pub struct GasConsumptionRate(u32);
+
+impl GasConsumptionRate {
+    pub const fn from_millilitres_per_minute(millilitres_per_minute: u32) -> Self {
+        GasConsumptionRate(millilitres_per_minute)
+    }
+
+    /// Create a rate from a value in litres per minute, rounded to the nearest millilitre per
+    /// minute.
+    pub fn from_litres_per_minute(litres_per_minute: f64) -> Self {
+        GasConsumptionRate((litres_per_minute * MILLILITRES_PER_LITRE).round().max(0.0) as u32)
+    }
+
+    /// Create a rate from a value in cubic feet per minute, rounded to the nearest millilitre per
+    /// minute.
+    pub fn from_cubic_feet_per_minute(cubic_feet_per_minute: f64) -> Self {
+        GasConsumptionRate::from_litres_per_minute(cubic_feet_per_minute * LITRES_PER_CUBIC_FOOT)
+    }
+
+    pub const fn millilitres_per_minute(&self) -> u32 {
+        self.0
+    }
+
+    pub fn litres_per_minute(&self) -> f64 {
+        self.0 as f64 / MILLILITRES_PER_LITRE
+    }
+
+    /// Rate in cubic feet per minute.
+    pub fn cubic_feet_per_minute(&self) -> f64 {
+        self.litres_per_minute() / LITRES_PER_CUBIC_FOOT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cubic_feet_per_minute_round_trip() {
+        for cubic_feet_per_minute in [0.35, 0.5, 0.65, 0.75, 1.0, 1.5] {
+            let rate = GasConsumptionRate::from_cubic_feet_per_minute(cubic_feet_per_minute);
+            assert!((rate.cubic_feet_per_minute() - cubic_feet_per_minute).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn cubic_feet_per_minute_are_not_rounded_to_whole_litres() {
+        // 0.5 cu ft/min is 14.158 L/min, which used to be stored as 14 L/min
+        let rate = GasConsumptionRate::from_cubic_feet_per_minute(0.5);
+        assert_eq!(rate.millilitres_per_minute(), 14158);
+        assert_ne!(rate, GasConsumptionRate::from_litres_per_minute(14.0));
+    }
+}
@@ -2,14 +2,53 @@ use crate::environment::Environment;
 use crate::units::pressure::{Pressure, WATER_VAPOUR_PRESSURE};
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
+/// Number of feet in a metre.
+pub const FEET_PER_METRE: f64 = 1.0 / 0.3048;
+
+/// Number of millimetres in a metre.
+const MILLIMETRES_PER_METRE: f64 = 1000.0;
+
+/// Depth of the surface.
+pub const SURFACE: Depth = Depth(0);
+
+/// Depth below the surface, stored as a whole number of millimetres.
+/// Keeping a fixed-point value means that depths (such as stop depths) can still be compared exactly.
 #[derive(Copy, Clone, Debug, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct Depth(pub u32);
+pub struct Depth(u32);
 
 impl Depth {
+    pub const fn from_millimetres(millimetres: u32) -> Self {
+        Depth(millimetres)
+    }
+
+    /// Create a depth from a value in metres, rounded to the nearest millimetre.
+    /// Negative values are clamped to the surface.
+    pub fn from_metres(metres: f64) -> Self {
+        Depth((metres * MILLIMETRES_PER_METRE).round().max(0.0) as u32)
+    }
+
+    /// Create a depth from a value in feet, rounded to the nearest millimetre.
+    pub fn from_feet(feet: f64) -> Self {
+        Depth::from_metres(feet / FEET_PER_METRE)
+    }
+
+    pub const fn millimetres(&self) -> u32 {
+        self.0
+    }
+
+    pub fn metres(&self) -> f64 {
+        self.0 as f64 / MILLIMETRES_PER_METRE
+    }
+
+    /// Depth in feet.
+    pub fn feet(&self) -> f64 {
+        self.metres() * FEET_PER_METRE
+    }
+
     pub fn pressure(&self, environment: Environment) -> Pressure {
-        Pressure(self.0 as f64 / environment.water_density().meters_per_bar())
-            + environment.altitude().atmospheric_pressure()
+        Pressure(self.metres() / environment.water_density().meters_per_bar())
+            + environment.surface_pressure()
     }
 
     pub fn compensated_pressure(&self, environment: Environment) -> Pressure {
@@ -49,30 +88,30 @@ impl SubAssign for Depth {
     }
 }
 
-impl Mul for Depth {
+impl Mul<u32> for Depth {
     type Output = Depth;
 
-    fn mul(self, rhs: Self) -> Self::Output {
-        Depth(self.0 * rhs.0)
+    fn mul(self, rhs: u32) -> Self::Output {
+        Depth(self.0 * rhs)
     }
 }
 
-impl MulAssign for Depth {
-    fn mul_assign(&mut self, rhs: Self) {
-        self.0 *= rhs.0
+impl MulAssign<u32> for Depth {
+    fn mul_assign(&mut self, rhs: u32) {
+        self.0 *= rhs
     }
 }
 
-impl Div for Depth {
+impl Div<u32> for Depth {
     type Output = Depth;
 
-    fn div(self, rhs: Self) -> Self::Output {
-        Depth(self.0 / rhs.0)
+    fn div(self, rhs: u32) -> Self::Output {
+        Depth(self.0 / rhs)
     }
 }
 
-impl DivAssign for Depth {
-    fn div_assign(&mut self, rhs: Self) {
-        self.0 /= rhs.0
+impl DivAssign<u32> for Depth {
+    fn div_assign(&mut self, rhs: u32) {
+        self.0 /= rhs
     }
 }
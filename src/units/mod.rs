@@ -4,4 +4,5 @@ pub mod consumption_rate;
 pub mod depth;
 pub mod pressure;
 pub mod rate;
+pub mod unit_system;
 pub mod water_density;
@@ -16,20 +16,32 @@ pub const PPO2_FUDGE_FACTOR: Pressure = Pressure(0.1);
 
 pub const WATER_VAPOUR_PRESSURE: Pressure = Pressure(0.06257);
 
+/// Number of pounds per square inch in a bar.
+pub const PSI_PER_BAR: f64 = 14.503_773_773;
+
 #[derive(Copy, Clone, Default, Debug, PartialOrd, PartialEq)]
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pressure(pub f64);
 
 impl Pressure {
+    /// Create a pressure from a value in pounds per square inch.
+    pub fn from_psi(psi: f64) -> Self {
+        Pressure(psi / PSI_PER_BAR)
+    }
+
+    /// Pressure in pounds per square inch.
+    pub fn psi(&self) -> f64 {
+        self.0 * PSI_PER_BAR
+    }
+
     pub fn equivalent_depth(&self, environment: Environment) -> Depth {
-        Depth(
-            ((self.0 as f64 - environment.altitude().atmospheric_pressure().0)
-                * environment.water_density().meters_per_bar()) as u32,
-        )
+        Depth::from_metres(self.precise_equivalent_depth(environment))
     }
 
+    /// Depth at which the ambient pressure equals this pressure (measured in metres).
+    /// Negative if the pressure is below the surface pressure.
     pub fn precise_equivalent_depth(&self, environment: Environment) -> f64 {
-        (self.0 - 1.0) * environment.water_density().meters_per_bar()
+        (self.0 - environment.surface_pressure().0) * environment.water_density().meters_per_bar()
     }
 }
 
@@ -1,8 +1,44 @@
+use crate::units::depth::FEET_PER_METRE;
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
+/// Number of millimetres in a metre.
+const MILLIMETRES_PER_METRE: f64 = 1000.0;
+
+/// Rate of depth change, stored as a whole number of millimetres per minute so that rates given
+/// in feet per minute keep their precision. Negative rates are ascents.
 #[derive(Copy, Clone, Debug, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct Rate(pub i32);
+pub struct Rate(i32);
+
+impl Rate {
+    pub const fn from_millimetres_per_minute(millimetres_per_minute: i32) -> Self {
+        Rate(millimetres_per_minute)
+    }
+
+    /// Create a rate from a value in metres per minute, rounded to the nearest millimetre per
+    /// minute.
+    pub fn from_metres_per_minute(metres_per_minute: f64) -> Self {
+        Rate((metres_per_minute * MILLIMETRES_PER_METRE).round() as i32)
+    }
+
+    /// Create a rate from a value in feet per minute, rounded to the nearest millimetre per minute.
+    pub fn from_feet_per_minute(feet_per_minute: f64) -> Self {
+        Rate::from_metres_per_minute(feet_per_minute / FEET_PER_METRE)
+    }
+
+    pub const fn millimetres_per_minute(&self) -> i32 {
+        self.0
+    }
+
+    pub fn metres_per_minute(&self) -> f64 {
+        self.0 as f64 / MILLIMETRES_PER_METRE
+    }
+
+    /// Rate in feet per minute.
+    pub fn feet_per_minute(&self) -> f64 {
+        self.metres_per_minute() * FEET_PER_METRE
+    }
+}
 
 impl Add for Rate {
     type Output = Rate;
@@ -32,30 +68,56 @@ impl SubAssign for Rate {
     }
 }
 
-impl Mul for Rate {
+impl Mul<i32> for Rate {
     type Output = Rate;
 
-    fn mul(self, rhs: Self) -> Self::Output {
-        Rate(self.0 * rhs.0)
+    fn mul(self, rhs: i32) -> Self::Output {
+        Rate(self.0 * rhs)
     }
 }
 
-impl MulAssign for Rate {
-    fn mul_assign(&mut self, rhs: Self) {
-        self.0 *= rhs.0
+impl MulAssign<i32> for Rate {
+    fn mul_assign(&mut self, rhs: i32) {
+        self.0 *= rhs
     }
 }
 
-impl Div for Rate {
+impl Div<i32> for Rate {
     type Output = Rate;
 
-    fn div(self, rhs: Self) -> Self::Output {
-        Rate(self.0 / rhs.0)
+    fn div(self, rhs: i32) -> Self::Output {
+        Rate(self.0 / rhs)
+    }
+}
+
+impl DivAssign<i32> for Rate {
+    fn div_assign(&mut self, rhs: i32) {
+        self.0 /= rhs
     }
 }
 
-impl DivAssign for Rate {
-    fn div_assign(&mut self, rhs: Self) {
-        self.0 /= rhs.0
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feet_per_minute_round_trip() {
+        for feet_per_minute in [-60.0, -33.0, -30.0, -10.0, 10.0, 30.0, 33.0, 60.0, 75.0] {
+            let rate = Rate::from_feet_per_minute(feet_per_minute);
+            assert!((rate.feet_per_minute() - feet_per_minute).abs() < 0.005);
+        }
+    }
+
+    #[test]
+    fn feet_per_minute_are_not_rounded_to_whole_metres() {
+        // 30 ft/min is 9.144 m/min, which used to be stored as 9 m/min
+        assert_eq!(
+            Rate::from_feet_per_minute(30.0).millimetres_per_minute(),
+            9144
+        );
+        assert_ne!(
+            Rate::from_feet_per_minute(30.0),
+            Rate::from_metres_per_minute(9.0)
+        );
     }
 }
@@ -0,0 +1,82 @@
+use crate::units::consumption::GasConsumption;
+use crate::units::consumption_rate::GasConsumptionRate;
+use crate::units::depth::Depth;
+use crate::units::pressure::Pressure;
+use crate::units::rate::Rate;
+
+/// System of units used to present results.
+/// Values are converted directly from the stored metric values, without intermediate rounding.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnitSystem {
+    /// Metres, bar, litres.
+    #[default]
+    Metric,
+    /// Feet, psi, cubic feet.
+    Imperial,
+}
+
+impl UnitSystem {
+    pub fn depth(&self, depth: Depth) -> f64 {
+        match self {
+            UnitSystem::Metric => depth.metres(),
+            UnitSystem::Imperial => depth.feet(),
+        }
+    }
+
+    pub fn pressure(&self, pressure: Pressure) -> f64 {
+        match self {
+            UnitSystem::Metric => pressure.0,
+            UnitSystem::Imperial => pressure.psi(),
+        }
+    }
+
+    pub fn rate(&self, rate: Rate) -> f64 {
+        match self {
+            UnitSystem::Metric => rate.metres_per_minute(),
+            UnitSystem::Imperial => rate.feet_per_minute(),
+        }
+    }
+
+    pub fn volume(&self, volume: GasConsumption) -> f64 {
+        match self {
+            UnitSystem::Metric => volume.0 as f64,
+            UnitSystem::Imperial => volume.cubic_feet(),
+        }
+    }
+
+    pub fn consumption_rate(&self, rate: GasConsumptionRate) -> f64 {
+        match self {
+            UnitSystem::Metric => rate.litres_per_minute(),
+            UnitSystem::Imperial => rate.cubic_feet_per_minute(),
+        }
+    }
+
+    pub fn depth_unit(&self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "m",
+            UnitSystem::Imperial => "ft",
+        }
+    }
+
+    pub fn pressure_unit(&self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "bar",
+            UnitSystem::Imperial => "psi",
+        }
+    }
+
+    pub fn rate_unit(&self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "m/min",
+            UnitSystem::Imperial => "ft/min",
+        }
+    }
+
+    pub fn volume_unit(&self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "L",
+            UnitSystem::Imperial => "cu ft",
+        }
+    }
+}
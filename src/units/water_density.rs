@@ -4,12 +4,91 @@ pub const SALTWATER: WaterDensity = WaterDensity(1023.6);
 /// Density of fresh water (measured in kg m^-3).
 pub const FRESHWATER: WaterDensity = WaterDensity(997.0);
 
+/// Density used by EN 13319 depth gauges and most dive computers (measured in kg m^-3).
+pub const EN13319: WaterDensity = WaterDensity(1020.0);
+
+/// Standard acceleration due to gravity (measured in m s^-2).
+const STANDARD_GRAVITY: f64 = 9.80665;
+
+/// Number of pascals in a bar.
+const PASCALS_PER_BAR: f64 = 100_000.0;
+
 #[derive(Copy, Clone, Default, Debug, PartialOrd, PartialEq)]
 #[cfg_attr(feature = "use-serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WaterDensity(pub f64);
 
 impl WaterDensity {
+    /// Density of sea water at atmospheric pressure, using the UNESCO (EOS-80) equation of state.
+    /// # Arguments
+    /// * `salinity` - Practical salinity (measured in PSU)
+    /// * `temperature` - Water temperature (measured in °C)
+    pub fn from_salinity(salinity: f64, temperature: f64) -> Self {
+        let t = temperature;
+        // Density of pure water
+        let pure = 999.842594 + 6.793952e-2 * t - 9.095290e-3 * t.powi(2) + 1.001685e-4 * t.powi(3)
+            - 1.120083e-6 * t.powi(4)
+            + 6.536332e-9 * t.powi(5);
+
+        let a = 8.24493e-1 - 4.0899e-3 * t + 7.6438e-5 * t.powi(2) - 8.2467e-7 * t.powi(3)
+            + 5.3875e-9 * t.powi(4);
+        let b = -5.72466e-3 + 1.0227e-4 * t - 1.6546e-6 * t.powi(2);
+        let c = 4.8314e-4;
+
+        WaterDensity(pure + a * salinity + b * salinity.powf(1.5) + c * salinity.powi(2))
+    }
+
+    /// Depth of water per bar of pressure. EN 13319 gauges work from the hydrostatic pressure of
+    /// their density under standard gravity (about 10.0 m/bar), while other densities keep the
+    /// traditional 10000/density approximation.
     pub fn meters_per_bar(&self) -> f64 {
-        10000.0 / self.0
+        if *self == EN13319 {
+            PASCALS_PER_BAR / (self.0 * STANDARD_GRAVITY)
+        } else {
+            10000.0 / self.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Environment;
+    use crate::units::altitude::Altitude;
+    use crate::units::depth::Depth;
+    use crate::units::pressure::Pressure;
+
+    #[test]
+    fn density_from_salinity() {
+        let density = WaterDensity::from_salinity(35.0, 25.0);
+        assert!((density.0 - 1023.3).abs() < 0.05, "{:?}", density);
+
+        let pure = WaterDensity::from_salinity(0.0, 4.0);
+        assert!((pure.0 - 1000.0).abs() < 0.05, "{:?}", pure);
+    }
+
+    #[test]
+    fn en13319_is_ten_metres_per_bar() {
+        assert!((EN13319.meters_per_bar() - 9.997).abs() < 0.001);
+        assert!((SALTWATER.meters_per_bar() - 10000.0 / 1023.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn en13319_depth_pressure_round_trip() {
+        let environment = Environment::new(EN13319, Altitude(0));
+        let surface = environment.surface_pressure();
+
+        for metres in [0.0, 3.0, 10.0, 21.0, 45.5, 100.0] {
+            let depth = Depth::from_metres(metres);
+            assert_eq!(
+                depth.pressure(environment).equivalent_depth(environment),
+                depth
+            );
+        }
+        let pressure = Depth::from_metres(30.0).pressure(environment) - surface;
+        assert!((pressure.0 - 3.0).abs() < 0.001, "{:?}", pressure);
+        assert_eq!(
+            (surface + Pressure(2.0)).equivalent_depth(environment),
+            Depth::from_metres(2.0 * EN13319.meters_per_bar())
+        );
     }
 }
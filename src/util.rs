@@ -11,6 +11,6 @@ use time::Duration;
 /// Function will panic if the time taken exceeds [`i64::MAX`].
 pub fn time_taken(rate: Rate, depth_1: Depth, depth_2: Depth) -> Duration {
     let delta_depth = depth_1.delta(depth_2);
-    let rate_seconds = rate.0.abs() as f64 / 60.0;
-    Duration::seconds((delta_depth.0 as f64 / rate_seconds) as i64)
+    let rate_seconds = rate.metres_per_minute().abs() / 60.0;
+    Duration::seconds((delta_depth.metres() / rate_seconds) as i64)
 }